@@ -0,0 +1,572 @@
+//! CiA 301 CANopen
+//!
+//! An 11 bit COB-ID is a 4 bit function code over a 7 bit node ID, so each node gets its own
+//! PDOs, SDO channel and heartbeat from the predefined connection set.
+//!
+//! PDOs are declared like any other frame, as a struct deriving CanDecode with `pdo = ..` and
+//! `node = ..` container attributes - a TPDO unless tagged `rpdo`. SDO frames are decoded one at
+//! a time with [`Sdo`], or followed across a whole transfer with [`SdoMonitor`]. The [`eds`]
+//! module generates the PDO structs from a device's EDS or DCF file instead.
+
+use crate::{CanDecode, CanDecodeSlice, Error};
+use std::collections::BTreeMap;
+
+pub mod eds;
+
+/// The SDO channel the server answers on, 0x580 plus the node
+const SDO_TX: u8 = 0xB;
+/// The SDO channel the client asks on, 0x600 plus the node
+const SDO_RX: u8 = 0xC;
+
+/// Data bytes in an SDO segment
+const SEGMENT_LEN: usize = 7;
+/// Data bytes in an expedited SDO transfer
+const EXPEDITED_LEN: usize = 4;
+
+/// What a COB-ID is for, from its top 4 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Nmt,
+    Sync,
+    Emergency,
+    Time,
+    /// Transmit PDOs 1 to 4, sent by the node
+    Tpdo(u8),
+    /// Receive PDOs 1 to 4, sent to the node
+    Rpdo(u8),
+    /// SDO responses from the node
+    SdoTx,
+    /// SDO requests to the node
+    SdoRx,
+    /// Heartbeat and node guarding
+    Heartbeat,
+    Other(u8),
+}
+
+/// An 11 bit COB-ID, split into its parts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CobId {
+    pub function: Function,
+    /// 1 to 127, or 0 for the broadcasts (NMT, SYNC and TIME)
+    pub node: u8,
+}
+
+impl CobId {
+    pub fn from_raw(id: u32) -> Self {
+        let node = (id & 0x7F) as u8;
+        let function = match ((id >> 7) & 0x0F) as u8 {
+            0x0 => Function::Nmt,
+            0x1 if node == 0 => Function::Sync,
+            0x1 => Function::Emergency,
+            0x2 => Function::Time,
+            code @ (0x3 | 0x5 | 0x7 | 0x9) => Function::Tpdo((code - 1) / 2),
+            code @ (0x4 | 0x6 | 0x8 | 0xA) => Function::Rpdo((code - 2) / 2),
+            SDO_TX => Function::SdoTx,
+            SDO_RX => Function::SdoRx,
+            0xE => Function::Heartbeat,
+            code => Function::Other(code),
+        };
+        Self { function, node }
+    }
+
+    pub fn to_raw(&self) -> u32 {
+        let code = match self.function {
+            Function::Nmt => 0x0,
+            Function::Sync | Function::Emergency => 0x1,
+            Function::Time => 0x2,
+            Function::Tpdo(number) => number * 2 + 1,
+            Function::Rpdo(number) => number * 2 + 2,
+            Function::SdoTx => SDO_TX,
+            Function::SdoRx => SDO_RX,
+            Function::Heartbeat => 0xE,
+            Function::Other(code) => code,
+        };
+        ((code as u32 & 0x0F) << 7) | (self.node as u32 & 0x7F)
+    }
+}
+
+/// Implemented by the derive for messages with `pdo` and `node` container attributes
+pub trait Pdo: CanDecode {
+    /// 1 to 4
+    const NUMBER: u8;
+    /// Whether the node sends the PDO (a TPDO) rather than receives it (an RPDO)
+    const TRANSMIT: bool;
+    const NODE: u8;
+
+    /// The COB-ID from the predefined connection set
+    fn cob_id() -> u32 {
+        let function = match Self::TRANSMIT {
+            true => Function::Tpdo(Self::NUMBER),
+            false => Function::Rpdo(Self::NUMBER),
+        };
+        CobId {
+            function,
+            node: Self::NODE,
+        }
+        .to_raw()
+    }
+
+    /// Whether a frame with this ID carries the PDO
+    fn matches(id: u32) -> bool {
+        id == Self::cob_id()
+    }
+
+    /// Decodes the frame when its ID carries the PDO, and gives None otherwise
+    fn from_canopen(id: u32, frame: [u8; 8]) -> Result<Option<Self>, Error> {
+        if !Self::matches(id) {
+            return Ok(None);
+        }
+        Self::from_socketcan(frame).map(Some)
+    }
+}
+
+/// How the data of an SDO initiate frame is sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Initiate {
+    /// Up to 4 bytes, carried in the initiate frame itself
+    Expedited(Vec<u8>),
+    /// Sent in segments after the initiate frame, with the total size when indicated
+    Segmented { size: Option<u32> },
+}
+
+/// A single SDO frame
+///
+/// Downloads write an object on the server, uploads read one. Requests go to the server on
+/// 0x600 plus the node, responses come back on 0x580 plus the node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sdo {
+    InitiateDownload {
+        index: u16,
+        subindex: u8,
+        data: Initiate,
+    },
+    InitiateDownloadResponse {
+        index: u16,
+        subindex: u8,
+    },
+    DownloadSegment {
+        toggle: bool,
+        last: bool,
+        data: Vec<u8>,
+    },
+    DownloadSegmentResponse {
+        toggle: bool,
+    },
+    InitiateUpload {
+        index: u16,
+        subindex: u8,
+    },
+    InitiateUploadResponse {
+        index: u16,
+        subindex: u8,
+        data: Initiate,
+    },
+    UploadSegment {
+        toggle: bool,
+    },
+    UploadSegmentResponse {
+        toggle: bool,
+        last: bool,
+        data: Vec<u8>,
+    },
+    /// Sent by either side to end a transfer
+    Abort {
+        index: u16,
+        subindex: u8,
+        code: u32,
+    },
+}
+
+impl Sdo {
+    /// Decodes a frame from the client, on 0x600 plus the node
+    pub fn from_request(frame: [u8; 8]) -> Result<Self, Error> {
+        let (index, subindex) = multiplexer(&frame);
+        Ok(match frame[0] >> 5 {
+            0 => Sdo::DownloadSegment {
+                toggle: toggle(frame[0]),
+                last: frame[0] & 0x01 != 0,
+                data: segment_data(&frame),
+            },
+            1 => Sdo::InitiateDownload {
+                index,
+                subindex,
+                data: initiate_data(&frame),
+            },
+            2 => Sdo::InitiateUpload { index, subindex },
+            3 => Sdo::UploadSegment {
+                toggle: toggle(frame[0]),
+            },
+            4 => abort(&frame),
+            _ => return Err(Error::UnexpectedFrame),
+        })
+    }
+
+    /// Decodes a frame from the server, on 0x580 plus the node
+    pub fn from_response(frame: [u8; 8]) -> Result<Self, Error> {
+        let (index, subindex) = multiplexer(&frame);
+        Ok(match frame[0] >> 5 {
+            0 => Sdo::UploadSegmentResponse {
+                toggle: toggle(frame[0]),
+                last: frame[0] & 0x01 != 0,
+                data: segment_data(&frame),
+            },
+            1 => Sdo::DownloadSegmentResponse {
+                toggle: toggle(frame[0]),
+            },
+            2 => Sdo::InitiateUploadResponse {
+                index,
+                subindex,
+                data: initiate_data(&frame),
+            },
+            3 => Sdo::InitiateDownloadResponse { index, subindex },
+            4 => abort(&frame),
+            _ => return Err(Error::UnexpectedFrame),
+        })
+    }
+
+    /// Lays the frame back out, failing on data too long for the frame
+    pub fn to_frame(&self) -> Result<[u8; 8], Error> {
+        let mut frame = [0u8; 8];
+        match self {
+            Sdo::InitiateDownload {
+                index,
+                subindex,
+                data,
+            }
+            | Sdo::InitiateUploadResponse {
+                index,
+                subindex,
+                data,
+            } => {
+                let command = match self {
+                    Sdo::InitiateDownload { .. } => 1,
+                    _ => 2,
+                };
+                frame[0] = command << 5;
+                set_multiplexer(&mut frame, *index, *subindex);
+                match data {
+                    Initiate::Expedited(data) => {
+                        // 1 to 4 bytes, as n only has room for up to 3 unused
+                        let unused = EXPEDITED_LEN
+                            .checked_sub(data.len())
+                            .filter(|unused| *unused < EXPEDITED_LEN)
+                            .ok_or(Error::ValueOutOfRange)?;
+                        frame[0] |= ((unused as u8) << 2) | 0x03;
+                        frame[4..4 + data.len()].copy_from_slice(data);
+                    }
+                    Initiate::Segmented { size: Some(size) } => {
+                        frame[0] |= 0x01;
+                        frame[4..].copy_from_slice(&size.to_le_bytes());
+                    }
+                    Initiate::Segmented { size: None } => {}
+                }
+            }
+            Sdo::DownloadSegment { toggle, last, data }
+            | Sdo::UploadSegmentResponse { toggle, last, data } => {
+                let unused = SEGMENT_LEN
+                    .checked_sub(data.len())
+                    .ok_or(Error::ValueOutOfRange)?;
+                // Both carry command specifier 0
+                frame[0] = ((*toggle as u8) << 4) | ((unused as u8) << 1) | *last as u8;
+                frame[1..1 + data.len()].copy_from_slice(data);
+            }
+            Sdo::DownloadSegmentResponse { toggle } => frame[0] = (1 << 5) | ((*toggle as u8) << 4),
+            Sdo::UploadSegment { toggle } => frame[0] = (3 << 5) | ((*toggle as u8) << 4),
+            Sdo::InitiateUpload { index, subindex } => {
+                frame[0] = 2 << 5;
+                set_multiplexer(&mut frame, *index, *subindex);
+            }
+            Sdo::InitiateDownloadResponse { index, subindex } => {
+                frame[0] = 3 << 5;
+                set_multiplexer(&mut frame, *index, *subindex);
+            }
+            Sdo::Abort {
+                index,
+                subindex,
+                code,
+            } => {
+                frame[0] = 4 << 5;
+                set_multiplexer(&mut frame, *index, *subindex);
+                frame[4..].copy_from_slice(&code.to_le_bytes());
+            }
+        }
+        Ok(frame)
+    }
+}
+
+/// Which way an SDO transfer moved the object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    /// Client to server, a write
+    Download,
+    /// Server to client, a read
+    Upload,
+}
+
+/// An object dictionary entry moved by a completed SDO transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub node: u8,
+    pub index: u16,
+    pub subindex: u8,
+    pub transfer: Transfer,
+    pub data: Vec<u8>,
+}
+
+impl Record {
+    /// The value of an integer object, which CANopen sends little endian
+    pub fn as_u32(&self) -> Option<u32> {
+        let mut bytes = [0u8; 4];
+        bytes
+            .get_mut(..self.data.len())?
+            .copy_from_slice(&self.data);
+        Some(u32::from_le_bytes(bytes))
+    }
+
+    pub fn decode<T: CanDecodeSlice>(&self) -> Result<T, Error> {
+        T::from_slice(&self.data)
+    }
+}
+
+/// Something out of a transfer the monitor is following
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Complete(Record),
+    Aborted {
+        node: u8,
+        index: u16,
+        subindex: u8,
+        code: u32,
+    },
+}
+
+/// A transfer in progress on one node's SDO channel
+#[derive(Debug)]
+struct Session {
+    index: u16,
+    subindex: u8,
+    transfer: Transfer,
+    data: Vec<u8>,
+    /// Toggle bit of the next segment
+    toggle: bool,
+    /// Set once all the data is in, for a download still waiting on the server to confirm
+    done: bool,
+}
+
+impl Session {
+    fn into_record(self, node: u8) -> Record {
+        Record {
+            node,
+            index: self.index,
+            subindex: self.subindex,
+            transfer: self.transfer,
+            data: self.data,
+        }
+    }
+}
+
+/// Follows the SDO transfers on the bus, from both sides, and hands back each object once its
+/// transfer completes
+///
+/// Downloads complete when the server confirms them, uploads when the server sends the last of
+/// the data. Transfers already in progress when the monitor starts are skipped.
+#[derive(Debug, Default)]
+pub struct SdoMonitor {
+    sessions: BTreeMap<u8, Session>,
+}
+
+impl SdoMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consumes a frame, ignoring anything that isn't on an SDO channel
+    pub fn push(&mut self, id: u32, frame: &[u8]) -> Result<Option<Event>, Error> {
+        let cob_id = CobId::from_raw(id);
+        if cob_id.function != Function::SdoTx && cob_id.function != Function::SdoRx {
+            return Ok(None);
+        }
+        let frame: [u8; 8] = frame
+            .get(..8)
+            .and_then(|frame| frame.try_into().ok())
+            .ok_or(Error::InvalidSlicingLength)?;
+        let sdo = match cob_id.function {
+            Function::SdoRx => Sdo::from_request(frame)?,
+            _ => Sdo::from_response(frame)?,
+        };
+        self.sdo(cob_id.node, sdo)
+    }
+
+    fn sdo(&mut self, node: u8, sdo: Sdo) -> Result<Option<Event>, Error> {
+        let complete = |session: Session| Some(Event::Complete(session.into_record(node)));
+
+        match sdo {
+            Sdo::InitiateDownload {
+                index,
+                subindex,
+                data,
+            } => {
+                let (data, done) = match data {
+                    Initiate::Expedited(data) => (data, true),
+                    Initiate::Segmented { .. } => (Vec::new(), false),
+                };
+                self.sessions.insert(
+                    node,
+                    Session {
+                        index,
+                        subindex,
+                        transfer: Transfer::Download,
+                        data,
+                        toggle: false,
+                        done,
+                    },
+                );
+                Ok(None)
+            }
+            Sdo::InitiateUpload { index, subindex } => {
+                self.sessions.insert(
+                    node,
+                    Session {
+                        index,
+                        subindex,
+                        transfer: Transfer::Upload,
+                        data: Vec::new(),
+                        toggle: false,
+                        done: false,
+                    },
+                );
+                Ok(None)
+            }
+            Sdo::InitiateDownloadResponse { .. } => match self.sessions.remove(&node) {
+                Some(session) if session.done => Ok(complete(session)),
+                // Segments follow
+                Some(session) => {
+                    self.sessions.insert(node, session);
+                    Ok(None)
+                }
+                None => Ok(None),
+            },
+            Sdo::InitiateUploadResponse {
+                index,
+                subindex,
+                data,
+            } => match data {
+                // Everything's here, so there's no need to have seen the request
+                Initiate::Expedited(data) => {
+                    self.sessions.remove(&node);
+                    Ok(complete(Session {
+                        index,
+                        subindex,
+                        transfer: Transfer::Upload,
+                        data,
+                        toggle: false,
+                        done: true,
+                    }))
+                }
+                Initiate::Segmented { .. } => Ok(None),
+            },
+            Sdo::DownloadSegment { toggle, last, data } => {
+                self.segment(node, Transfer::Download, toggle, last, data)
+            }
+            Sdo::UploadSegmentResponse { toggle, last, data } => {
+                self.segment(node, Transfer::Upload, toggle, last, data)
+            }
+            Sdo::DownloadSegmentResponse { .. } => match self.sessions.remove(&node) {
+                Some(session) if session.done => Ok(complete(session)),
+                Some(session) => {
+                    self.sessions.insert(node, session);
+                    Ok(None)
+                }
+                None => Ok(None),
+            },
+            Sdo::UploadSegment { .. } => Ok(None),
+            Sdo::Abort {
+                index,
+                subindex,
+                code,
+            } => {
+                self.sessions.remove(&node);
+                Ok(Some(Event::Aborted {
+                    node,
+                    index,
+                    subindex,
+                    code,
+                }))
+            }
+        }
+    }
+
+    /// Appends a segment's data, completing an upload on its last segment
+    fn segment(
+        &mut self,
+        node: u8,
+        transfer: Transfer,
+        toggle: bool,
+        last: bool,
+        data: Vec<u8>,
+    ) -> Result<Option<Event>, Error> {
+        let Some(session) = self.sessions.get_mut(&node) else {
+            return Ok(None);
+        };
+        if session.transfer != transfer || session.toggle != toggle || session.done {
+            self.sessions.remove(&node);
+            return Err(Error::UnexpectedFrame);
+        }
+        session.data.extend_from_slice(&data);
+        session.toggle = !toggle;
+        session.done = last;
+        match (transfer, last) {
+            (Transfer::Upload, true) => Ok(self
+                .sessions
+                .remove(&node)
+                .map(|session| Event::Complete(session.into_record(node)))),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Index and subindex, from bytes 1 to 3
+fn multiplexer(frame: &[u8; 8]) -> (u16, u8) {
+    (u16::from_le_bytes([frame[1], frame[2]]), frame[3])
+}
+
+fn set_multiplexer(frame: &mut [u8; 8], index: u16, subindex: u8) {
+    frame[1..3].copy_from_slice(&index.to_le_bytes());
+    frame[3] = subindex;
+}
+
+fn toggle(command: u8) -> bool {
+    command & 0x10 != 0
+}
+
+/// The data of an initiate frame, going by its e (expedited) and s (size indicated) bits
+fn initiate_data(frame: &[u8; 8]) -> Initiate {
+    let expedited = frame[0] & 0x02 != 0;
+    let size_indicated = frame[0] & 0x01 != 0;
+    match (expedited, size_indicated) {
+        (true, true) => {
+            let unused = ((frame[0] >> 2) & 0x03) as usize;
+            Initiate::Expedited(frame[4..8 - unused].to_vec())
+        }
+        // The size is left to the object's type, so hand back all of it
+        (true, false) => Initiate::Expedited(frame[4..].to_vec()),
+        (false, true) => Initiate::Segmented {
+            size: Some(u32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]])),
+        },
+        (false, false) => Initiate::Segmented { size: None },
+    }
+}
+
+fn segment_data(frame: &[u8; 8]) -> Vec<u8> {
+    let unused = ((frame[0] >> 1) & 0x07) as usize;
+    frame[1..8 - unused].to_vec()
+}
+
+fn abort(frame: &[u8; 8]) -> Sdo {
+    let (index, subindex) = multiplexer(frame);
+    Sdo::Abort {
+        index,
+        subindex,
+        code: u32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]),
+    }
+}
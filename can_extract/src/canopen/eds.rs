@@ -0,0 +1,471 @@
+//! CiA 306 electronic data sheets (EDS) and device configuration files (DCF)
+//!
+//! Both are INI files with a section per object dictionary entry - `[6041]` for a variable,
+//! `[1A00sub1]` for each subindex of a record or array. The PDO mapping records (0x1600 for
+//! RPDOs, 0x1A00 for TPDOs) list the mapped objects as `index << 16 | subindex << 8 | bits`,
+//! packed into the frame from bit 0 up.
+//!
+//! [`Eds::generate`] turns the mappings into structs deriving CanDecode and CanEncode, meant to
+//! be written out by a build script and pulled in with `include!`, alongside
+//! `use proc_macro_derive_crate::{CanDecode, CanEncode};`.
+
+use crate::Error;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// First RPDO mapping record, one per RPDO from here
+const RPDO_MAPPING: u16 = 0x1600;
+/// First TPDO mapping record
+const TPDO_MAPPING: u16 = 0x1A00;
+/// First RPDO and TPDO communication records, holding the COB-ID in subindex 1
+const RPDO_COMMUNICATION: u16 = 0x1400;
+const TPDO_COMMUNICATION: u16 = 0x1800;
+/// PDOs a device can have in each direction
+const MAX_PDOS: u16 = 512;
+/// Set in a PDO's COB-ID when the PDO isn't in use
+const COB_ID_INVALID: u32 = 1 << 31;
+
+/// Basic data types a PDO can map, by their CiA 301 index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    Boolean,
+    Integer8,
+    Integer16,
+    Integer24,
+    Integer32,
+    Integer64,
+    Unsigned8,
+    Unsigned16,
+    Unsigned24,
+    Unsigned32,
+    Unsigned64,
+    Real32,
+    Real64,
+}
+
+impl DataType {
+    /// None for the types a PDO can't map, like strings and domains
+    pub fn from_index(index: u16) -> Option<Self> {
+        Some(match index {
+            0x0001 => DataType::Boolean,
+            0x0002 => DataType::Integer8,
+            0x0003 => DataType::Integer16,
+            0x0004 => DataType::Integer32,
+            0x0005 => DataType::Unsigned8,
+            0x0006 => DataType::Unsigned16,
+            0x0007 => DataType::Unsigned32,
+            0x0008 => DataType::Real32,
+            0x0010 => DataType::Integer24,
+            0x0011 => DataType::Real64,
+            0x0015 => DataType::Integer64,
+            0x0016 => DataType::Unsigned24,
+            0x001B => DataType::Unsigned64,
+            _ => return None,
+        })
+    }
+
+    /// The member type the generated struct uses
+    pub fn rust_type(&self) -> &'static str {
+        match self {
+            DataType::Boolean => "bool",
+            DataType::Integer8 => "i8",
+            DataType::Integer16 => "i16",
+            DataType::Integer24 | DataType::Integer32 => "i32",
+            DataType::Integer64 => "i64",
+            DataType::Unsigned8 => "u8",
+            DataType::Unsigned16 => "u16",
+            DataType::Unsigned24 | DataType::Unsigned32 => "u32",
+            DataType::Unsigned64 => "u64",
+            DataType::Real32 => "f32",
+            DataType::Real64 => "f64",
+        }
+    }
+}
+
+/// An object mapped into a PDO
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappedObject {
+    pub index: u16,
+    pub subindex: u8,
+    pub start_bit: u16,
+    /// Length in bits
+    pub length: u8,
+    pub name: String,
+    /// None for dummy entries, which only pad the frame out
+    pub data_type: Option<DataType>,
+}
+
+/// A PDO's mapping and COB-ID, as configured in the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdoMapping {
+    /// 1 to 512
+    pub number: u16,
+    /// Whether the node sends the PDO (a TPDO) rather than receives it (an RPDO)
+    pub transmit: bool,
+    pub cob_id: u32,
+    pub objects: Vec<MappedObject>,
+}
+
+impl PdoMapping {
+    /// The COB-ID from the predefined connection set, for PDOs 1 to 4
+    fn default_cob_id(&self, node: u8) -> Option<u32> {
+        let base = match self.transmit {
+            true => 0x180,
+            false => 0x200,
+        };
+        (1..=4)
+            .contains(&self.number)
+            .then(|| base + (self.number as u32 - 1) * 0x100 + node as u32)
+    }
+}
+
+/// A parsed EDS or DCF file
+#[derive(Debug, Clone, Default)]
+pub struct Eds {
+    /// Object sections by index and subindex, None for a `[6041]` style variable
+    objects: BTreeMap<(u16, Option<u8>), BTreeMap<String, String>>,
+    /// The rest, like `[DeviceInfo]`, by upper case name
+    sections: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Eds {
+    /// Reads the sections and their keys, which are matched case insensitively
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut eds = Self::default();
+        let mut section = None;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[') {
+                let name = name
+                    .strip_suffix(']')
+                    .ok_or(Error::InvalidObjectDictionary)?
+                    .trim()
+                    .to_uppercase();
+                section = Some(match object_key(&name) {
+                    Some(key) => Ok(key),
+                    None => Err(name),
+                });
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or(Error::InvalidObjectDictionary)?;
+            let entries = match section.as_ref().ok_or(Error::InvalidObjectDictionary)? {
+                Ok(key) => eds.objects.entry(*key).or_default(),
+                Err(name) => eds.sections.entry(name.clone()).or_default(),
+            };
+            entries.insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+        Ok(eds)
+    }
+
+    /// The node ID a DCF was configured with
+    pub fn node_id(&self) -> Option<u8> {
+        let value = self.sections.get("DEVICECOMISSIONING")?.get("nodeid")?;
+        parse_value(value, 0).and_then(|node| u8::try_from(node).ok())
+    }
+
+    /// A key of an object's section, e.g. `parametername`
+    pub fn get(&self, index: u16, subindex: u8, key: &str) -> Option<&str> {
+        let section = self.objects.get(&(index, Some(subindex))).or_else(|| {
+            // A variable's section has no subindex
+            match subindex {
+                0 => self.objects.get(&(index, None)),
+                _ => None,
+            }
+        })?;
+        section.get(&key.to_lowercase()).map(String::as_str)
+    }
+
+    /// An object's value, the configured ParameterValue of a DCF over the DefaultValue
+    pub fn value(&self, index: u16, subindex: u8, node: u8) -> Option<u64> {
+        let value = self
+            .get(index, subindex, "ParameterValue")
+            .or_else(|| self.get(index, subindex, "DefaultValue"))?;
+        parse_value(value, node)
+    }
+
+    /// The PDOs with at least one object mapped, skipping those whose COB-ID marks them unused
+    pub fn pdos(&self, node: u8) -> Result<Vec<PdoMapping>, Error> {
+        let mut pdos = Vec::new();
+        for (transmit, mapping, communication) in [
+            (false, RPDO_MAPPING, RPDO_COMMUNICATION),
+            (true, TPDO_MAPPING, TPDO_COMMUNICATION),
+        ] {
+            for offset in 0..MAX_PDOS {
+                // Unused PDOs needn't have a communication record, so skip them before looking
+                // for their COB-ID
+                let Some(count) = self
+                    .value(mapping + offset, 0, node)
+                    .filter(|&count| count != 0)
+                else {
+                    continue;
+                };
+                let mut pdo = PdoMapping {
+                    number: offset + 1,
+                    transmit,
+                    cob_id: 0,
+                    objects: Vec::new(),
+                };
+                let default_cob_id = pdo.default_cob_id(node);
+                let cob_id = match self.value(communication + offset, 1, node) {
+                    Some(cob_id) => cob_id as u32,
+                    None => default_cob_id.ok_or(Error::InvalidObjectDictionary)?,
+                };
+                if cob_id & COB_ID_INVALID != 0 {
+                    continue;
+                }
+                pdo.cob_id = cob_id & 0x1FFF_FFFF;
+
+                let mut start_bit = 0;
+                for subindex in 1..=count {
+                    let entry = u8::try_from(subindex)
+                        .ok()
+                        .and_then(|subindex| self.value(mapping + offset, subindex, node))
+                        .ok_or(Error::InvalidObjectDictionary)?;
+                    let object = self.mapped_object(entry as u32, start_bit, node)?;
+                    start_bit += object.length as u16;
+                    pdo.objects.push(object);
+                }
+                if start_bit > 64 {
+                    return Err(Error::InvalidObjectDictionary);
+                }
+                pdos.push(pdo);
+            }
+        }
+        Ok(pdos)
+    }
+
+    fn mapped_object(&self, entry: u32, start_bit: u16, node: u8) -> Result<MappedObject, Error> {
+        let index = (entry >> 16) as u16;
+        let subindex = (entry >> 8) as u8;
+        let length = entry as u8;
+        let mut object = MappedObject {
+            index,
+            subindex,
+            start_bit,
+            length,
+            name: String::new(),
+            data_type: None,
+        };
+        // Indexes below 0x20 are the data types themselves, mapped as padding
+        if index < 0x20 {
+            return Ok(object);
+        }
+        let data_type = self
+            .get(index, subindex, "DataType")
+            .and_then(|data_type| parse_value(data_type, node))
+            .and_then(|data_type| DataType::from_index(data_type as u16))
+            .ok_or(Error::InvalidObjectDictionary)?;
+        let width = match data_type {
+            DataType::Boolean => 1,
+            DataType::Integer24 | DataType::Unsigned24 => 24,
+            other => rust_width(other.rust_type()),
+        };
+        // Floats can't be cut short
+        let float = matches!(data_type, DataType::Real32 | DataType::Real64);
+        if length == 0 || length > width || (float && length != width) {
+            return Err(Error::InvalidObjectDictionary);
+        }
+        object.name = self
+            .get(index, subindex, "ParameterName")
+            .unwrap_or_default()
+            .to_string();
+        object.data_type = Some(data_type);
+        Ok(object)
+    }
+
+    /// Rust source for a struct per PDO, named `{prefix}Tpdo1` and so on
+    pub fn generate(&self, node: u8, prefix: &str) -> Result<String, Error> {
+        let mut source = String::new();
+        for pdo in self.pdos(node)? {
+            let kind = match pdo.transmit {
+                true => "Tpdo",
+                false => "Rpdo",
+            };
+            let mapping = match pdo.transmit {
+                true => TPDO_MAPPING,
+                false => RPDO_MAPPING,
+            } + pdo.number
+                - 1;
+            let attribute = match pdo.default_cob_id(node) {
+                Some(cob_id) if cob_id == pdo.cob_id && pdo.transmit => {
+                    format!("pdo = {}, node = {:#04X}", pdo.number, node)
+                }
+                Some(cob_id) if cob_id == pdo.cob_id => {
+                    format!("pdo = {}, node = {:#04X}, rpdo", pdo.number, node)
+                }
+                _ if pdo.cob_id > 0x7FF => format!("id = {:#010X}", pdo.cob_id),
+                _ => format!("id = {:#05X}", pdo.cob_id),
+            };
+            let floats = pdo.objects.iter().any(|object| {
+                matches!(object.data_type, Some(DataType::Real32 | DataType::Real64))
+            });
+
+            if !source.is_empty() {
+                source.push('\n');
+            }
+            let _ = writeln!(
+                source,
+                "/// {}{} of node {}, mapped by {:#06X}",
+                kind.to_uppercase(),
+                pdo.number,
+                node,
+                mapping
+            );
+            let derives = match floats {
+                true => "CanDecode, CanEncode, Debug, Clone, Copy, PartialEq",
+                false => "CanDecode, CanEncode, Debug, Clone, Copy, PartialEq, Eq",
+            };
+            let _ = writeln!(source, "#[derive({})]", derives);
+            let _ = writeln!(source, "#[can_extract({})]", attribute);
+            let _ = writeln!(source, "pub struct {}{}{} {{", prefix, kind, pdo.number);
+
+            let mut names = Vec::new();
+            for object in &pdo.objects {
+                let Some(data_type) = object.data_type else {
+                    continue;
+                };
+                let mut name = field_name(&object.name);
+                if name.is_empty() || names.contains(&name) {
+                    // The same object can be mapped more than once, so number the copies
+                    let fallback = format!("object_{:04x}_{:02x}", object.index, object.subindex);
+                    name = fallback.clone();
+                    for copy in 2.. {
+                        if !names.contains(&name) {
+                            break;
+                        }
+                        name = format!("{}_{}", fallback, copy);
+                    }
+                }
+                let ty = data_type.rust_type();
+                let placement = if matches!(data_type, DataType::Real32 | DataType::Real64) {
+                    format!("start_bit = {}, ieee_float", object.start_bit)
+                } else if data_type != DataType::Boolean
+                    && object.start_bit % 8 == 0
+                    && object.length == rust_width(ty)
+                {
+                    format!("offset = {}", object.start_bit / 8)
+                } else {
+                    format!(
+                        "start_bit = {}, length = {}",
+                        object.start_bit, object.length
+                    )
+                };
+                let _ = writeln!(
+                    source,
+                    "    /// {:#06X}:{:02X} {}",
+                    object.index, object.subindex, object.name
+                );
+                let _ = writeln!(source, "    #[can_extract({})]", placement);
+                let _ = writeln!(source, "    pub {}: {},", name, ty);
+                names.push(name);
+            }
+            let _ = writeln!(source, "}}");
+        }
+        Ok(source)
+    }
+}
+
+/// The index and subindex an object section is named for, `6041` or `1A00sub1`
+fn object_key(name: &str) -> Option<(u16, Option<u8>)> {
+    let (index, subindex) = match name.split_once("SUB") {
+        Some((index, subindex)) => (index, Some(u8::from_str_radix(subindex, 16).ok()?)),
+        None => (name, None),
+    };
+    if index.len() != 4 {
+        return None;
+    }
+    Some((u16::from_str_radix(index, 16).ok()?, subindex))
+}
+
+/// Integers are decimal, 0x hex or 0 octal, and may add `$NODEID` in
+fn parse_value(value: &str, node: u8) -> Option<u64> {
+    if value.is_empty() {
+        return None;
+    }
+    value.split('+').try_fold(0u64, |sum, term| {
+        let term = term.trim();
+        let term = if term.eq_ignore_ascii_case("$NODEID") {
+            node as u64
+        } else if let Some(hex) = term.strip_prefix("0x").or(term.strip_prefix("0X")) {
+            u64::from_str_radix(hex, 16).ok()?
+        } else if term.len() > 1 && term.starts_with('0') {
+            u64::from_str_radix(&term[1..], 8).ok()?
+        } else {
+            term.parse().ok()?
+        };
+        sum.checked_add(term)
+    })
+}
+
+fn rust_width(ty: &str) -> u8 {
+    match ty {
+        "bool" => 1,
+        "i8" | "u8" => 8,
+        "i16" | "u16" => 16,
+        "i32" | "u32" | "f32" => 32,
+        _ => 64,
+    }
+}
+
+/// A snake case member name out of a ParameterName, empty when nothing usable is left
+fn field_name(parameter_name: &str) -> String {
+    let mut name = String::new();
+    for c in parameter_name.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name = name.trim_end_matches('_').to_string();
+    match name.chars().next() {
+        Some(first) if first.is_ascii_digit() => format!("_{}", name),
+        _ if is_keyword(&name) => format!("{}_", name),
+        _ => name,
+    }
+}
+
+fn is_keyword(name: &str) -> bool {
+    matches!(
+        name,
+        "as" | "break"
+            | "const"
+            | "continue"
+            | "crate"
+            | "else"
+            | "enum"
+            | "extern"
+            | "false"
+            | "fn"
+            | "for"
+            | "if"
+            | "impl"
+            | "in"
+            | "let"
+            | "loop"
+            | "match"
+            | "mod"
+            | "move"
+            | "mut"
+            | "pub"
+            | "ref"
+            | "return"
+            | "self"
+            | "static"
+            | "struct"
+            | "super"
+            | "trait"
+            | "true"
+            | "type"
+            | "unsafe"
+            | "use"
+            | "where"
+            | "while"
+            | "async"
+            | "await"
+            | "dyn"
+    )
+}
@@ -0,0 +1,197 @@
+//! Typed conversions between a member and its raw signal value
+//!
+//! A codec is named on a member with `#[can_extract(codec = Scaled<1, 1000>)]`. The derive
+//! extracts the raw value (from whole bytes or a bit range, in the member's byte order) and
+//! hands it to the codec, so the codec never has to care where the signal lives.
+//!
+//! `factor = 0.01` is shorthand for `codec = Scaled<1, 100>`. A member can also be a uom quantity
+//! written out as `uom::si::f32::Velocity`, with the `uom` feature - the codec then works on the
+//! `f32` in the unit named by `unit = "kilometer_per_hour"`, and the derive converts it to and
+//! from the quantity, so mixing up km/h and m/s is a type error rather than a bug.
+
+use crate::{Error, Scaling};
+use std::marker::PhantomData;
+
+/// Converts a member to and from the raw value of its signal
+pub trait FieldCodec<T> {
+    /// Scaling from raw to physical, reported through CanLayout
+    const SCALING: Option<Scaling> = None;
+    /// Whether the raw value is two's complement, reported through CanLayout
+    const SIGNED: bool = false;
+
+    /// Decodes the member from the low `length` bits of `raw`
+    fn decode(raw: u64, length: u16) -> Result<T, Error>;
+    /// Encodes the member as a raw value that fits in `length` bits
+    fn encode(value: &T, length: u16) -> Result<u64, Error>;
+}
+
+/// Largest raw value that fits in `length` bits
+pub(crate) fn max_raw(length: u16) -> u64 {
+    u64::MAX >> (64 - length.clamp(1, 64))
+}
+
+/// Sign extends the low `length` bits of `raw`
+fn sign_extend(raw: u64, length: u16) -> i64 {
+    let shift = 64 - length.clamp(1, 64) as u32;
+    ((raw << shift) as i64) >> shift
+}
+
+/// Unsigned linear scaling, `physical = raw * NUM / DEN + OFFSET`
+pub struct Scaled<const NUM: i64, const DEN: i64, const OFFSET: i64 = 0>;
+
+/// Two's complement linear scaling, `physical = raw * NUM / DEN + OFFSET`
+pub struct ScaledSigned<const NUM: i64, const DEN: i64, const OFFSET: i64 = 0>;
+
+macro_rules! impl_scaled {
+    ($($ty:ty),*) => {$(
+        impl<const NUM: i64, const DEN: i64, const OFFSET: i64> FieldCodec<$ty>
+            for Scaled<NUM, DEN, OFFSET>
+        {
+            const SCALING: Option<Scaling> = Some(Scaling {
+                factor: NUM as f64 / DEN as f64,
+                offset: OFFSET as f64,
+            });
+
+            fn decode(raw: u64, _length: u16) -> Result<$ty, Error> {
+                Ok((raw as f64 * NUM as f64 / DEN as f64 + OFFSET as f64) as $ty)
+            }
+
+            fn encode(value: &$ty, length: u16) -> Result<u64, Error> {
+                let raw = ((*value as f64 - OFFSET as f64) * DEN as f64 / NUM as f64).round();
+                if !(0.0..=max_raw(length) as f64).contains(&raw) {
+                    return Err(Error::ValueOutOfRange);
+                }
+                Ok(raw as u64)
+            }
+        }
+
+        impl<const NUM: i64, const DEN: i64, const OFFSET: i64> FieldCodec<$ty>
+            for ScaledSigned<NUM, DEN, OFFSET>
+        {
+            const SCALING: Option<Scaling> = Some(Scaling {
+                factor: NUM as f64 / DEN as f64,
+                offset: OFFSET as f64,
+            });
+            const SIGNED: bool = true;
+
+            fn decode(raw: u64, length: u16) -> Result<$ty, Error> {
+                let raw = sign_extend(raw, length);
+                Ok((raw as f64 * NUM as f64 / DEN as f64 + OFFSET as f64) as $ty)
+            }
+
+            fn encode(value: &$ty, length: u16) -> Result<u64, Error> {
+                let raw = ((*value as f64 - OFFSET as f64) * DEN as f64 / NUM as f64).round();
+                let max = (max_raw(length) >> 1) as f64;
+                if !(-max - 1.0..=max).contains(&raw) {
+                    return Err(Error::ValueOutOfRange);
+                }
+                Ok(raw as i64 as u64 & max_raw(length))
+            }
+        }
+    )*};
+}
+
+impl_scaled!(f32, f64);
+
+/// Binary coded decimal, one digit per nibble
+pub struct Bcd;
+
+macro_rules! impl_bcd {
+    ($($ty:ty),*) => {$(
+        impl FieldCodec<$ty> for Bcd {
+            fn decode(raw: u64, length: u16) -> Result<$ty, Error> {
+                let mut value: u64 = 0;
+                for nibble in (0..length.div_ceil(4)).rev() {
+                    let digit = (raw >> (nibble * 4)) & 0xF;
+                    if digit > 9 {
+                        return Err(Error::InvalidBytesConversion);
+                    }
+                    value = value * 10 + digit;
+                }
+                <$ty>::try_from(value).map_err(|_| Error::ValueOutOfRange)
+            }
+
+            fn encode(value: &$ty, length: u16) -> Result<u64, Error> {
+                let mut value = *value as u64;
+                let mut raw = 0;
+                let mut shift = 0;
+                while value > 0 {
+                    // More digits than fit, which would also overflow the shift past 16 digits
+                    if shift >= length.min(64) {
+                        return Err(Error::ValueOutOfRange);
+                    }
+                    raw |= (value % 10) << shift;
+                    value /= 10;
+                    shift += 4;
+                }
+                if raw > max_raw(length) {
+                    return Err(Error::ValueOutOfRange);
+                }
+                Ok(raw)
+            }
+        }
+    )*};
+}
+
+impl_bcd!(u8, u16, u32, u64);
+
+/// An enum (or any type) with conversions to and from the raw value, e.g. a DBC value table
+/// Implement `TryFrom<u64>` for the decode side, and `From<&T> for u64` for the encode side
+pub struct Enumerated<T>(PhantomData<T>);
+
+impl<T> FieldCodec<T> for Enumerated<T>
+where
+    T: TryFrom<u64>,
+    for<'a> u64: From<&'a T>,
+{
+    fn decode(raw: u64, _length: u16) -> Result<T, Error> {
+        T::try_from(raw).map_err(|_| Error::InvalidBytesConversion)
+    }
+
+    fn encode(value: &T, length: u16) -> Result<u64, Error> {
+        let raw = u64::from(value);
+        if raw > max_raw(length) {
+            return Err(Error::ValueOutOfRange);
+        }
+        Ok(raw)
+    }
+}
+
+/// IEEE-754 floats, a 32 bit signal for f32 and a 64 bit signal for f64
+pub struct Ieee754;
+
+impl FieldCodec<f32> for Ieee754 {
+    const SCALING: Option<Scaling> = Some(Scaling::IDENTITY);
+
+    fn decode(raw: u64, length: u16) -> Result<f32, Error> {
+        if length != 32 {
+            return Err(Error::InvalidSlicingLength);
+        }
+        Ok(f32::from_bits(raw as u32))
+    }
+
+    fn encode(value: &f32, length: u16) -> Result<u64, Error> {
+        if length != 32 {
+            return Err(Error::InvalidSlicingLength);
+        }
+        Ok(value.to_bits() as u64)
+    }
+}
+
+impl FieldCodec<f64> for Ieee754 {
+    const SCALING: Option<Scaling> = Some(Scaling::IDENTITY);
+
+    fn decode(raw: u64, length: u16) -> Result<f64, Error> {
+        if length != 64 {
+            return Err(Error::InvalidSlicingLength);
+        }
+        Ok(f64::from_bits(raw))
+    }
+
+    fn encode(value: &f64, length: u16) -> Result<u64, Error> {
+        if length != 64 {
+            return Err(Error::InvalidSlicingLength);
+        }
+        Ok(value.to_bits())
+    }
+}
@@ -0,0 +1,199 @@
+//! AUTOSAR E2E protection, profiles 1 and 2
+//!
+//! Both profiles use the same layout on an 8 byte frame - the CRC sits in byte 0 and the
+//! 4 bit alive counter in the low nibble of byte 1. Everything else is payload.
+
+use crate::{CanDecode, Error};
+
+/// Byte holding the CRC
+const CRC_BYTE: usize = 0;
+/// Byte holding the alive counter, in its low nibble
+const COUNTER_BYTE: usize = 1;
+
+/// How the 16 bit data ID of a profile 1 message goes into the CRC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataIdMode {
+    /// Low byte, then high byte
+    Both,
+    /// Low byte on even counters, high byte on odd counters
+    Alt,
+    /// Low byte only
+    Low,
+}
+
+/// The E2E profile a message is protected with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// CRC8 SAE J1850 over the data ID and payload, counter 0..=14
+    Profile1 { data_id: u16, mode: DataIdMode },
+    /// CRC8H2F over the payload and a data ID picked by counter, counter 0..=15
+    Profile2 { data_id_list: [u8; 16] },
+}
+
+/// Everything the check and protect state machines need to know about a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub profile: Profile,
+    /// Largest counter jump that is still treated as OK (i.e. lost frames we tolerate)
+    pub max_delta_counter: u8,
+}
+
+/// Result of checking a received frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// CRC matched and the counter moved on by an acceptable amount
+    Ok,
+    /// CRC matched but the counter did not change
+    Repeated,
+    /// CRC matched but the counter jumped further than `max_delta_counter`
+    WrongSequence,
+    /// CRC or counter value is invalid
+    Error,
+    /// Nothing was received since the last check
+    NoNewData,
+}
+
+/// Implemented by the derive for messages with an `e2e_profile` container attribute
+pub trait E2eProtected: CanDecode {
+    const E2E: Config;
+}
+
+impl Profile {
+    /// Counter values wrap at this modulus
+    fn counter_modulus(&self) -> u8 {
+        match self {
+            Profile::Profile1 { .. } => 15,
+            Profile::Profile2 { .. } => 16,
+        }
+    }
+
+    /// Computes the CRC over a frame for a given counter value
+    fn crc(&self, frame: &[u8], counter: u8) -> u8 {
+        match self {
+            Profile::Profile1 { data_id, mode } => {
+                let [low, high] = data_id.to_le_bytes();
+                let crc = match mode {
+                    DataIdMode::Both => crc8(0x1D, 0x00, &[low, high]),
+                    DataIdMode::Alt if counter & 1 == 0 => crc8(0x1D, 0x00, &[low]),
+                    DataIdMode::Alt => crc8(0x1D, 0x00, &[high]),
+                    DataIdMode::Low => crc8(0x1D, 0x00, &[low]),
+                };
+                crc8(0x1D, crc, &frame[CRC_BYTE + 1..])
+            }
+            Profile::Profile2 { data_id_list } => {
+                let crc = crc8(0x2F, 0xFF, &frame[CRC_BYTE + 1..]);
+                crc8(0x2F, crc, &[data_id_list[counter as usize]]) ^ 0xFF
+            }
+        }
+    }
+}
+
+/// Plain MSB-first CRC8 with no reflection, continuing from `crc`
+fn crc8(poly: u8, mut crc: u8, data: &[u8]) -> u8 {
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Receive side state machine - tracks the last counter seen for a single message
+#[derive(Debug, Clone)]
+pub struct Checker {
+    config: Config,
+    last_counter: Option<u8>,
+}
+
+impl Checker {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            last_counter: None,
+        }
+    }
+
+    /// Builds a checker from the config declared on a derived message
+    pub fn for_message<T: E2eProtected>() -> Self {
+        Self::new(T::E2E)
+    }
+
+    /// Checks a frame, or the lack of one, and advances the state machine
+    pub fn check(&mut self, frame: Option<&[u8]>) -> Status {
+        let Some(frame) = frame else {
+            return Status::NoNewData;
+        };
+        if frame.len() <= COUNTER_BYTE {
+            return Status::Error;
+        }
+
+        let counter = frame[COUNTER_BYTE] & 0x0F;
+        let modulus = self.config.profile.counter_modulus();
+        if counter >= modulus || self.config.profile.crc(frame, counter) != frame[CRC_BYTE] {
+            return Status::Error;
+        }
+
+        let Some(last) = self.last_counter else {
+            self.last_counter = Some(counter);
+            return Status::Ok;
+        };
+
+        let delta = (counter + modulus - last) % modulus;
+        if delta == 0 {
+            return Status::Repeated;
+        }
+        self.last_counter = Some(counter);
+        if delta <= self.config.max_delta_counter {
+            Status::Ok
+        } else {
+            Status::WrongSequence
+        }
+    }
+
+    /// Checks a frame and decodes it, giving back the status alongside the message
+    ///
+    /// The message is decoded whenever a frame was received, even if the check failed, so the
+    /// caller decides what to do with it based on the status.
+    pub fn check_decode<T: CanDecode>(
+        &mut self,
+        frame: Option<[u8; 8]>,
+    ) -> Result<(Status, Option<T>), Error> {
+        let status = self.check(frame.as_ref().map(|f| &f[..]));
+        let value = frame.map(T::from_socketcan).transpose()?;
+        Ok((status, value))
+    }
+}
+
+/// Send side state machine - owns the counter for a single message
+#[derive(Debug, Clone)]
+pub struct Protector {
+    config: Config,
+    counter: u8,
+}
+
+impl Protector {
+    pub fn new(config: Config) -> Self {
+        Self { config, counter: 0 }
+    }
+
+    /// Builds a protector from the config declared on a derived message
+    pub fn for_message<T: E2eProtected>() -> Self {
+        Self::new(T::E2E)
+    }
+
+    /// Writes the counter and CRC into a frame, then advances the counter
+    pub fn protect(&mut self, frame: &mut [u8]) -> Result<(), Error> {
+        if frame.len() <= COUNTER_BYTE {
+            return Err(Error::InvalidSlicingLength);
+        }
+        frame[COUNTER_BYTE] = (frame[COUNTER_BYTE] & 0xF0) | self.counter;
+        frame[CRC_BYTE] = self.config.profile.crc(frame, self.counter);
+        self.counter = (self.counter + 1) % self.config.profile.counter_modulus();
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+//! Member types the derive reads and writes directly, without a codec or decoder
+//!
+//! Every plain member is located as a bit range - `offset = ..` members are just whole bytes,
+//! starting at the LSB of the offset byte for Intel and the MSB for Motorola - and read through
+//! [`CanField`]. Implementing it for your own types lets them be used as members directly:
+//!
+//! ```
+//! use can_extract::{ByteOrder, CanField, Error};
+//!
+//! struct Percent(u8);
+//!
+//! impl CanField for Percent {
+//!     const BITS: u16 = 8;
+//!
+//!     fn decode(frame: &[u8], start_bit: u16, length: u16, byte_order: ByteOrder) -> Result<Self, Error> {
+//!         match u8::decode(frame, start_bit, length, byte_order)? {
+//!             raw @ 0..=100 => Ok(Percent(raw)),
+//!             _ => Err(Error::ValueOutOfRange),
+//!         }
+//!     }
+//!
+//!     fn encode(&self, frame: &mut [u8], start_bit: u16, length: u16, byte_order: ByteOrder) -> Result<(), Error> {
+//!         self.0.encode(frame, start_bit, length, byte_order)
+//!     }
+//! }
+//! ```
+
+use crate::helper::{extract_bits, insert_bits, RawBits};
+use crate::signal::{Ranges, Signal};
+use crate::{ByteOrder, Error};
+
+/// A member read from, and written back to, a bit range of the frame
+///
+/// Bit ranges follow DBC semantics, see [`crate::helper::extract_bits`].
+pub trait CanField: Sized {
+    /// Bits the member takes up, unless it declares a `length` or `extract`
+    const BITS: u16;
+
+    fn decode(
+        frame: &[u8],
+        start_bit: u16,
+        length: u16,
+        byte_order: ByteOrder,
+    ) -> Result<Self, Error>;
+    fn encode(
+        &self,
+        frame: &mut [u8],
+        start_bit: u16,
+        length: u16,
+        byte_order: ByteOrder,
+    ) -> Result<(), Error>;
+}
+
+/// Start bit of whatever follows `bits` bits on from `start_bit`
+fn advance(start_bit: u16, bits: u16, byte_order: ByteOrder) -> u16 {
+    match byte_order {
+        ByteOrder::LittleEndian => start_bit + bits,
+        ByteOrder::BigEndian => {
+            // Step along the straightened out sawtooth, then back to a DBC start bit
+            let msb = (start_bit / 8) * 8 + (7 - start_bit % 8) + bits;
+            (msb / 8) * 8 + (7 - msb % 8)
+        }
+    }
+}
+
+macro_rules! impl_can_field_int {
+    ($($ty:ty),*) => {$(
+        impl CanField for $ty {
+            const BITS: u16 = <$ty>::BITS as u16;
+
+            fn decode(frame: &[u8], start_bit: u16, length: u16, byte_order: ByteOrder) -> Result<Self, Error> {
+                if length > <Self as CanField>::BITS {
+                    return Err(Error::InvalidSlicingLength);
+                }
+                let raw = extract_bits(frame, start_bit, length, byte_order)?;
+                Ok(<$ty as RawBits>::from_raw(raw, length))
+            }
+
+            fn encode(&self, frame: &mut [u8], start_bit: u16, length: u16, byte_order: ByteOrder) -> Result<(), Error> {
+                if length > <Self as CanField>::BITS {
+                    return Err(Error::InvalidSlicingLength);
+                }
+                insert_bits(frame, start_bit, length, byte_order, RawBits::to_raw(self))
+            }
+        }
+    )*};
+}
+
+impl_can_field_int!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+macro_rules! impl_can_field_wide {
+    ($($ty:ty => $unsigned:ty),*) => {$(
+        impl CanField for $ty {
+            const BITS: u16 = <$ty>::BITS as u16;
+
+            fn decode(frame: &[u8], start_bit: u16, length: u16, byte_order: ByteOrder) -> Result<Self, Error> {
+                if length == 0 || length > <Self as CanField>::BITS {
+                    return Err(Error::InvalidSlicingLength);
+                }
+                // Read as two halves of up to 64 bits, the low half first for Intel
+                let high_len = length.saturating_sub(64);
+                let low_len = length - high_len;
+                let (low_start, high_start) = match byte_order {
+                    ByteOrder::LittleEndian => (start_bit, advance(start_bit, low_len, byte_order)),
+                    ByteOrder::BigEndian => (advance(start_bit, high_len, byte_order), start_bit),
+                };
+                let mut raw = extract_bits(frame, low_start, low_len, byte_order)? as u128;
+                if high_len > 0 {
+                    raw |= (extract_bits(frame, high_start, high_len, byte_order)? as u128) << 64;
+                }
+                // Sign extend out of the declared length, a no-op for unsigned types
+                let shift = 128 - length as u32;
+                Ok((((raw << shift) as $ty) >> shift) as $ty)
+            }
+
+            fn encode(&self, frame: &mut [u8], start_bit: u16, length: u16, byte_order: ByteOrder) -> Result<(), Error> {
+                if length == 0 || length > <Self as CanField>::BITS {
+                    return Err(Error::InvalidSlicingLength);
+                }
+                let raw = *self as $unsigned;
+                let high_len = length.saturating_sub(64);
+                let low_len = length - high_len;
+                let (low_start, high_start) = match byte_order {
+                    ByteOrder::LittleEndian => (start_bit, advance(start_bit, low_len, byte_order)),
+                    ByteOrder::BigEndian => (advance(start_bit, high_len, byte_order), start_bit),
+                };
+                insert_bits(frame, low_start, low_len, byte_order, raw as u64)?;
+                if high_len > 0 {
+                    insert_bits(frame, high_start, high_len, byte_order, (raw >> 64) as u64)?;
+                }
+                Ok(())
+            }
+        }
+    )*};
+}
+
+impl_can_field_wide!(u128 => u128, i128 => u128);
+
+macro_rules! impl_can_field_size {
+    ($($ty:ty => $fixed:ty),*) => {$(
+        /// Read as a 64 bit integer, so the layout doesn't depend on the target
+        impl CanField for $ty {
+            const BITS: u16 = 64;
+
+            fn decode(frame: &[u8], start_bit: u16, length: u16, byte_order: ByteOrder) -> Result<Self, Error> {
+                let value = <$fixed>::decode(frame, start_bit, length, byte_order)?;
+                value.try_into().map_err(|_| Error::ValueOutOfRange)
+            }
+
+            fn encode(&self, frame: &mut [u8], start_bit: u16, length: u16, byte_order: ByteOrder) -> Result<(), Error> {
+                let value = <$fixed>::try_from(*self).map_err(|_| Error::ValueOutOfRange)?;
+                value.encode(frame, start_bit, length, byte_order)
+            }
+        }
+    )*};
+}
+
+impl_can_field_size!(usize => u64, isize => i64);
+
+macro_rules! impl_can_field_float {
+    ($($ty:ty => $bits:ty),*) => {$(
+        /// IEEE 754, which only fits its full width
+        impl CanField for $ty {
+            const BITS: u16 = <$bits>::BITS as u16;
+
+            fn decode(frame: &[u8], start_bit: u16, length: u16, byte_order: ByteOrder) -> Result<Self, Error> {
+                if length != <Self as CanField>::BITS {
+                    return Err(Error::InvalidBytesConversion);
+                }
+                let raw = extract_bits(frame, start_bit, length, byte_order)?;
+                Ok(<$ty>::from_bits(raw as $bits))
+            }
+
+            fn encode(&self, frame: &mut [u8], start_bit: u16, length: u16, byte_order: ByteOrder) -> Result<(), Error> {
+                if length != <Self as CanField>::BITS {
+                    return Err(Error::InvalidBytesConversion);
+                }
+                insert_bits(frame, start_bit, length, byte_order, self.to_bits() as u64)
+            }
+        }
+    )*};
+}
+
+impl_can_field_float!(f32 => u32, f64 => u64);
+
+impl CanField for bool {
+    const BITS: u16 = 1;
+
+    fn decode(
+        frame: &[u8],
+        start_bit: u16,
+        length: u16,
+        byte_order: ByteOrder,
+    ) -> Result<Self, Error> {
+        Ok(extract_bits(frame, start_bit, length, byte_order)? != 0)
+    }
+
+    fn encode(
+        &self,
+        frame: &mut [u8],
+        start_bit: u16,
+        length: u16,
+        byte_order: ByteOrder,
+    ) -> Result<(), Error> {
+        insert_bits(frame, start_bit, length, byte_order, *self as u64)
+    }
+}
+
+/// Elements laid out back to back, the first at `start_bit`
+impl<T: CanField, const N: usize> CanField for [T; N] {
+    const BITS: u16 = T::BITS * N as u16;
+
+    fn decode(
+        frame: &[u8],
+        start_bit: u16,
+        length: u16,
+        byte_order: ByteOrder,
+    ) -> Result<Self, Error> {
+        if length != <Self as CanField>::BITS {
+            return Err(Error::InvalidSlicingLength);
+        }
+        let mut items = Vec::with_capacity(N);
+        for index in 0..N as u16 {
+            let start_bit = advance(start_bit, index * T::BITS, byte_order);
+            items.push(T::decode(frame, start_bit, T::BITS, byte_order)?);
+        }
+        items.try_into().map_err(|_| Error::InvalidSlicingLength)
+    }
+
+    fn encode(
+        &self,
+        frame: &mut [u8],
+        start_bit: u16,
+        length: u16,
+        byte_order: ByteOrder,
+    ) -> Result<(), Error> {
+        if length != <Self as CanField>::BITS {
+            return Err(Error::InvalidSlicingLength);
+        }
+        for (index, item) in self.iter().enumerate() {
+            let start_bit = advance(start_bit, index as u16 * T::BITS, byte_order);
+            item.encode(frame, start_bit, T::BITS, byte_order)?;
+        }
+        Ok(())
+    }
+}
+
+/// None for the raw values J1939 reserves, the same as an `Option<T>` member - so not available,
+/// error and reserved all read as None, and a value encoding to one of them can't be sent.
+/// Anything wider than 64 bits is None only when every bit is set
+impl<T: CanField> CanField for Option<T> {
+    const BITS: u16 = T::BITS;
+
+    fn decode(
+        frame: &[u8],
+        start_bit: u16,
+        length: u16,
+        byte_order: ByteOrder,
+    ) -> Result<Self, Error> {
+        if length <= 64 {
+            let raw = extract_bits(frame, start_bit, length, byte_order)?;
+            return match Ranges::J1939.classify(raw, length) {
+                Signal::Valid(_) => T::decode(frame, start_bit, length, byte_order).map(Some),
+                _ => Ok(None),
+            };
+        }
+        let mut all_set = true;
+        let mut index = 0;
+        while all_set && index < length {
+            let chunk = (length - index).min(64);
+            let start_bit = advance(start_bit, index, byte_order);
+            all_set =
+                extract_bits(frame, start_bit, chunk, byte_order)? == u64::MAX >> (64 - chunk);
+            index += chunk;
+        }
+        match all_set {
+            true => Ok(None),
+            false => T::decode(frame, start_bit, length, byte_order).map(Some),
+        }
+    }
+
+    fn encode(
+        &self,
+        frame: &mut [u8],
+        start_bit: u16,
+        length: u16,
+        byte_order: ByteOrder,
+    ) -> Result<(), Error> {
+        if let Some(value) = self {
+            value.encode(frame, start_bit, length, byte_order)?;
+            if length <= 64 {
+                let raw = extract_bits(frame, start_bit, length, byte_order)?;
+                if !Ranges::J1939.classify(raw, length).is_valid() {
+                    return Err(Error::ValueOutOfRange);
+                }
+            }
+            return Ok(());
+        }
+        let mut index = 0;
+        while index < length {
+            let chunk = (length - index).min(64);
+            let start_bit = advance(start_bit, index, byte_order);
+            insert_bits(frame, start_bit, chunk, byte_order, u64::MAX)?;
+            index += chunk;
+        }
+        Ok(())
+    }
+}
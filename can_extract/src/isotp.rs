@@ -0,0 +1,436 @@
+//! ISO 15765-2 (ISO-TP) segmentation and reassembly
+//!
+//! Payloads of up to 7 bytes go in a single frame. Longer ones start with a first frame
+//! carrying the length, then the receiver paces the consecutive frames with flow control -
+//! how many to send per block, and the minimum gap (STmin) between them.
+//!
+//! Both sides are plain state machines. They send through a [`FrameSink`], take frames from
+//! the caller (or a [`FrameSource`]) and are told the time, so they run just as well over a
+//! socket as over a pair of in-memory queues.
+
+use crate::Error;
+use core::time::Duration;
+use std::collections::VecDeque;
+
+const SINGLE_FRAME: u8 = 0x0;
+const FIRST_FRAME: u8 = 0x1;
+const CONSECUTIVE_FRAME: u8 = 0x2;
+const FLOW_CONTROL: u8 = 0x3;
+
+const CLEAR_TO_SEND: u8 = 0x0;
+const WAIT: u8 = 0x1;
+const OVERFLOW: u8 = 0x2;
+
+/// Payload bytes in a single frame, or after the header of a consecutive frame
+const FRAME_DATA_LEN: usize = 7;
+/// Longest payload a first frame can announce without the 32 bit escape
+const SHORT_FIRST_FRAME_MAX: usize = 0xFFF;
+
+/// Where frames go, e.g. a socket or a queue
+pub trait FrameSink {
+    fn send_frame(&mut self, frame: [u8; 8]) -> Result<(), Error>;
+}
+
+/// Where frames come from
+pub trait FrameSource {
+    fn next_frame(&mut self) -> Option<[u8; 8]>;
+}
+
+impl FrameSink for VecDeque<[u8; 8]> {
+    fn send_frame(&mut self, frame: [u8; 8]) -> Result<(), Error> {
+        self.push_back(frame);
+        Ok(())
+    }
+}
+
+impl FrameSource for VecDeque<[u8; 8]> {
+    fn next_frame(&mut self) -> Option<[u8; 8]> {
+        self.pop_front()
+    }
+}
+
+/// Settings shared by both sides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Consecutive frames asked for per flow control, 0 for all of them
+    pub block_size: u8,
+    /// Gap asked for between consecutive frames
+    pub st_min: Duration,
+    /// Fills the unused bytes of short frames
+    pub padding: u8,
+    /// Longest payload the receiver takes, anything longer is refused with an overflow
+    pub max_len: usize,
+    /// How long to wait for the other side's next frame (N_Bs and N_Cr)
+    pub timeout: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            block_size: 0,
+            st_min: Duration::ZERO,
+            padding: 0xCC,
+            max_len: SHORT_FIRST_FRAME_MAX,
+            timeout: Duration::from_millis(1000),
+        }
+    }
+}
+
+/// STmin on the wire - whole milliseconds up to 127, or 100 to 900 microseconds
+pub fn encode_st_min(st_min: Duration) -> u8 {
+    let micros = st_min.as_micros();
+    match micros {
+        100..=900 if micros.is_multiple_of(100) => 0xF0 + (micros / 100) as u8,
+        _ => st_min.as_millis().min(0x7F) as u8,
+    }
+}
+
+/// Reserved values are treated as the longest gap, as the standard asks
+pub fn decode_st_min(st_min: u8) -> Duration {
+    match st_min {
+        0x00..=0x7F => Duration::from_millis(st_min as u64),
+        0xF1..=0xF9 => Duration::from_micros((st_min - 0xF0) as u64 * 100),
+        _ => Duration::from_millis(0x7F),
+    }
+}
+
+/// Receive side, reassembling one payload at a time
+#[derive(Debug)]
+pub struct Receiver {
+    config: Config,
+    state: Option<Reception>,
+}
+
+#[derive(Debug)]
+struct Reception {
+    len: usize,
+    data: Vec<u8>,
+    next_sequence: u8,
+    /// Consecutive frames left before the next flow control, when the block size isn't 0
+    block_remaining: u8,
+    deadline: Duration,
+}
+
+impl Receiver {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            state: None,
+        }
+    }
+
+    /// Consumes a frame received at `now`, giving back the payload once it's complete
+    pub fn on_frame(
+        &mut self,
+        now: Duration,
+        frame: &[u8],
+        sink: &mut impl FrameSink,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        if self
+            .state
+            .as_ref()
+            .is_some_and(|state| state.deadline < now)
+        {
+            self.state = None;
+        }
+        let pci = *frame.first().ok_or(Error::InvalidSlicingLength)?;
+
+        match pci >> 4 {
+            SINGLE_FRAME => {
+                // A new single frame ends whatever was in progress
+                self.state = None;
+                let len = (pci & 0x0F) as usize;
+                if len == 0 || len > FRAME_DATA_LEN {
+                    return Err(Error::InvalidSlicingLength);
+                }
+                let data = frame.get(1..1 + len).ok_or(Error::InvalidSlicingLength)?;
+                Ok(Some(data.to_vec()))
+            }
+            FIRST_FRAME => {
+                self.state = None;
+                let short_len =
+                    (((pci & 0x0F) as usize) << 8) | *frame.get(1).unwrap_or(&0) as usize;
+                let (len, data) = match short_len {
+                    0 => {
+                        let len = frame.get(2..6).ok_or(Error::InvalidSlicingLength)?;
+                        let len = u32::from_be_bytes(len.try_into().expect("4 bytes")) as usize;
+                        (len, frame.get(6..).unwrap_or_default())
+                    }
+                    len => (len, frame.get(2..).unwrap_or_default()),
+                };
+                if len <= FRAME_DATA_LEN {
+                    return Err(Error::InvalidSlicingLength);
+                }
+                if len > self.config.max_len {
+                    self.flow_control(OVERFLOW, sink)?;
+                    return Err(Error::TransportOverflow);
+                }
+
+                let mut reception = Reception {
+                    len,
+                    data: Vec::with_capacity(len),
+                    next_sequence: 1,
+                    block_remaining: self.config.block_size,
+                    deadline: now + self.config.timeout,
+                };
+                reception.data.extend_from_slice(data);
+                self.state = Some(reception);
+                self.flow_control(CLEAR_TO_SEND, sink)?;
+                Ok(None)
+            }
+            CONSECUTIVE_FRAME => {
+                let Some(reception) = self.state.as_mut() else {
+                    // Not receiving anything, so it isn't for us
+                    return Ok(None);
+                };
+                if pci & 0x0F != reception.next_sequence {
+                    self.state = None;
+                    return Err(Error::UnexpectedFrame);
+                }
+
+                let remaining = reception.len - reception.data.len();
+                let data = frame.get(1..).unwrap_or_default();
+                reception
+                    .data
+                    .extend_from_slice(&data[..remaining.min(data.len())]);
+                reception.next_sequence = (reception.next_sequence + 1) & 0x0F;
+                reception.deadline = now + self.config.timeout;
+
+                if reception.data.len() == reception.len {
+                    return Ok(self.state.take().map(|reception| reception.data));
+                }
+                if self.config.block_size != 0 {
+                    reception.block_remaining -= 1;
+                    if reception.block_remaining == 0 {
+                        reception.block_remaining = self.config.block_size;
+                        self.flow_control(CLEAR_TO_SEND, sink)?;
+                    }
+                }
+                Ok(None)
+            }
+            // Flow control is for the sending side
+            _ => Ok(None),
+        }
+    }
+
+    /// Takes frames from `source` until it runs dry or a payload is complete
+    pub fn receive(
+        &mut self,
+        now: Duration,
+        source: &mut impl FrameSource,
+        sink: &mut impl FrameSink,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        while let Some(frame) = source.next_frame() {
+            if let Some(payload) = self.on_frame(now, &frame, sink)? {
+                return Ok(Some(payload));
+            }
+        }
+        self.poll(now)?;
+        Ok(None)
+    }
+
+    /// Drops a reception the sender stopped part way through
+    pub fn poll(&mut self, now: Duration) -> Result<(), Error> {
+        if self
+            .state
+            .as_ref()
+            .is_some_and(|state| state.deadline < now)
+        {
+            self.state = None;
+            return Err(Error::TransportTimeout);
+        }
+        Ok(())
+    }
+
+    fn flow_control(&self, status: u8, sink: &mut impl FrameSink) -> Result<(), Error> {
+        let mut frame = [self.config.padding; 8];
+        frame[0] = (FLOW_CONTROL << 4) | status;
+        frame[1] = self.config.block_size;
+        frame[2] = encode_st_min(self.config.st_min);
+        sink.send_frame(frame)
+    }
+}
+
+/// Send side, segmenting one payload at a time
+#[derive(Debug)]
+pub struct Sender {
+    config: Config,
+    data: Vec<u8>,
+    /// Bytes of `data` already sent
+    offset: usize,
+    next_sequence: u8,
+    state: SendState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SendState {
+    Idle,
+    WaitFlowControl {
+        deadline: Duration,
+    },
+    Sending {
+        /// Consecutive frames left in this block, None when the receiver didn't limit them
+        block_remaining: Option<u8>,
+        st_min: Duration,
+        next_at: Duration,
+    },
+}
+
+impl Sender {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            data: Vec::new(),
+            offset: 0,
+            next_sequence: 1,
+            state: SendState::Idle,
+        }
+    }
+
+    /// Whether the last payload has gone out in full
+    pub fn is_idle(&self) -> bool {
+        self.state == SendState::Idle
+    }
+
+    /// Starts sending a payload, as a single frame or a first frame
+    pub fn send(
+        &mut self,
+        now: Duration,
+        payload: &[u8],
+        sink: &mut impl FrameSink,
+    ) -> Result<(), Error> {
+        if !self.is_idle() {
+            return Err(Error::TransportBusy);
+        }
+        let mut frame = [self.config.padding; 8];
+
+        if payload.len() <= FRAME_DATA_LEN {
+            frame[0] = (SINGLE_FRAME << 4) | payload.len() as u8;
+            frame[1..1 + payload.len()].copy_from_slice(payload);
+            return sink.send_frame(frame);
+        }
+
+        let header = if payload.len() <= SHORT_FIRST_FRAME_MAX {
+            frame[0] = (FIRST_FRAME << 4) | (payload.len() >> 8) as u8;
+            frame[1] = payload.len() as u8;
+            2
+        } else {
+            let len = u32::try_from(payload.len()).map_err(|_| Error::ValueOutOfRange)?;
+            frame[0] = FIRST_FRAME << 4;
+            frame[1] = 0;
+            frame[2..6].copy_from_slice(&len.to_be_bytes());
+            6
+        };
+        let first = 8 - header;
+        frame[header..].copy_from_slice(&payload[..first]);
+        sink.send_frame(frame)?;
+
+        self.data = payload.to_vec();
+        self.offset = first;
+        self.next_sequence = 1;
+        self.state = SendState::WaitFlowControl {
+            deadline: now + self.config.timeout,
+        };
+        Ok(())
+    }
+
+    /// Consumes a frame received at `now`, sending whatever flow control now allows
+    pub fn on_frame(
+        &mut self,
+        now: Duration,
+        frame: &[u8],
+        sink: &mut impl FrameSink,
+    ) -> Result<(), Error> {
+        let pci = *frame.first().ok_or(Error::InvalidSlicingLength)?;
+        if pci >> 4 != FLOW_CONTROL || !matches!(self.state, SendState::WaitFlowControl { .. }) {
+            return Ok(());
+        }
+
+        match pci & 0x0F {
+            CLEAR_TO_SEND => {
+                let block_size = *frame.get(1).ok_or(Error::InvalidSlicingLength)?;
+                let st_min = *frame.get(2).ok_or(Error::InvalidSlicingLength)?;
+                self.state = SendState::Sending {
+                    block_remaining: (block_size != 0).then_some(block_size),
+                    st_min: decode_st_min(st_min),
+                    next_at: now,
+                };
+                self.poll(now, sink)
+            }
+            WAIT => {
+                self.state = SendState::WaitFlowControl {
+                    deadline: now + self.config.timeout,
+                };
+                Ok(())
+            }
+            OVERFLOW => {
+                self.state = SendState::Idle;
+                Err(Error::TransportOverflow)
+            }
+            _ => {
+                self.state = SendState::Idle;
+                Err(Error::UnexpectedFrame)
+            }
+        }
+    }
+
+    /// Sends the consecutive frames due by `now`, or gives up on a silent receiver
+    pub fn poll(&mut self, now: Duration, sink: &mut impl FrameSink) -> Result<(), Error> {
+        match self.state {
+            SendState::WaitFlowControl { deadline } if deadline < now => {
+                self.state = SendState::Idle;
+                Err(Error::TransportTimeout)
+            }
+            SendState::Sending {
+                mut block_remaining,
+                st_min,
+                mut next_at,
+            } => {
+                while next_at <= now {
+                    let end = (self.offset + FRAME_DATA_LEN).min(self.data.len());
+                    let mut frame = [self.config.padding; 8];
+                    frame[0] = (CONSECUTIVE_FRAME << 4) | self.next_sequence;
+                    frame[1..1 + end - self.offset].copy_from_slice(&self.data[self.offset..end]);
+                    sink.send_frame(frame)?;
+                    self.offset = end;
+                    self.next_sequence = (self.next_sequence + 1) & 0x0F;
+                    next_at = now + st_min;
+
+                    if self.offset == self.data.len() {
+                        self.state = SendState::Idle;
+                        return Ok(());
+                    }
+                    if let Some(remaining) = block_remaining.as_mut() {
+                        *remaining -= 1;
+                        if *remaining == 0 {
+                            self.state = SendState::WaitFlowControl {
+                                deadline: now + self.config.timeout,
+                            };
+                            return Ok(());
+                        }
+                    }
+                }
+                self.state = SendState::Sending {
+                    block_remaining,
+                    st_min,
+                    next_at,
+                };
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Takes flow control frames from `source`, then sends what's due
+    pub fn pump(
+        &mut self,
+        now: Duration,
+        source: &mut impl FrameSource,
+        sink: &mut impl FrameSink,
+    ) -> Result<(), Error> {
+        while let Some(frame) = source.next_frame() {
+            self.on_frame(now, &frame, sink)?;
+        }
+        self.poll(now, sink)
+    }
+}
@@ -0,0 +1,84 @@
+//! SAE J1939 addressing
+//!
+//! J1939 packs a priority, a parameter group number (PGN) and the sender's source address into
+//! the 29 bit extended ID. PDU1 PGNs (PDU format below 240) are peer to peer and carry a
+//! destination address in place of the PGN's low byte. PDU2 PGNs are always broadcast.
+
+use crate::{CanDecode, Error};
+
+pub mod dm1;
+pub mod tp;
+
+pub use dm1::Dm1;
+
+/// Destination address meaning every node
+pub const GLOBAL_ADDRESS: u8 = 0xFF;
+
+/// A 29 bit J1939 ID, split into its parts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Id {
+    /// 0 is the highest priority, 7 the lowest
+    pub priority: u8,
+    /// Parameter group number, with the low byte zeroed for PDU1 PGNs
+    pub pgn: u32,
+    pub source: u8,
+    /// Where a PDU1 PGN is going, None for PDU2 PGNs
+    pub destination: Option<u8>,
+}
+
+impl Id {
+    pub fn from_raw(id: u32) -> Self {
+        let priority = ((id >> 26) & 0x07) as u8;
+        let pgn = (id >> 8) & 0x3_FFFF;
+        let source = (id & 0xFF) as u8;
+        if is_pdu1(pgn) {
+            Self {
+                priority,
+                pgn: pgn & 0x3_FF00,
+                source,
+                destination: Some((pgn & 0xFF) as u8),
+            }
+        } else {
+            Self {
+                priority,
+                pgn,
+                source,
+                destination: None,
+            }
+        }
+    }
+
+    pub fn to_raw(&self) -> u32 {
+        let pgn = match is_pdu1(self.pgn) {
+            true => (self.pgn & 0x3_FF00) | self.destination.unwrap_or(GLOBAL_ADDRESS) as u32,
+            false => self.pgn & 0x3_FFFF,
+        };
+        ((self.priority as u32 & 0x07) << 26) | (pgn << 8) | self.source as u32
+    }
+}
+
+/// Whether a PGN is peer to peer, with the destination address in its low byte
+pub fn is_pdu1(pgn: u32) -> bool {
+    (pgn >> 8) & 0xFF < 240
+}
+
+/// Implemented by the derive for messages with a `pgn` container attribute
+pub trait J1939Message {
+    const PGN: u32;
+
+    /// Whether a frame with this ID carries the message, whichever node sent it
+    fn matches(id: u32) -> bool {
+        Id::from_raw(id).pgn == Self::PGN
+    }
+
+    /// Decodes the frame when its ID carries the message, and gives None otherwise
+    fn from_j1939(id: u32, frame: [u8; 8]) -> Result<Option<Self>, Error>
+    where
+        Self: CanDecode,
+    {
+        if !Self::matches(id) {
+            return Ok(None);
+        }
+        Self::from_socketcan(frame).map(Some)
+    }
+}
@@ -0,0 +1,193 @@
+//! J1939-73 DM1, the active diagnostic trouble codes
+//!
+//! Two bytes of lamp status are followed by 4 bytes per DTC. A single DTC fits in one frame
+//! (padded with 0xFF), more than that arrive through the transport protocol - so Dm1 decodes
+//! from either an 8 byte frame or a reassembled payload.
+
+use super::J1939Message;
+use crate::codec::Enumerated;
+use crate::{CanDecode, CanDecodeSlice, CanEncode, Error, Signal};
+use proc_macro_derive_crate::{CanDecode, CanEncode};
+
+pub const PGN_DM1: u32 = 0xFECA;
+
+/// Bytes of lamp status before the first DTC
+const LAMP_LEN: usize = 2;
+/// Bytes per DTC
+const DTC_LEN: usize = 4;
+
+/// Whether a lamp is lit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lamp {
+    Off,
+    On,
+}
+
+/// How fast a lit lamp flashes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flash {
+    Slow,
+    Fast,
+}
+
+impl TryFrom<u64> for Lamp {
+    type Error = ();
+
+    fn try_from(raw: u64) -> Result<Self, ()> {
+        match raw {
+            0 => Ok(Lamp::Off),
+            1 => Ok(Lamp::On),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<&Lamp> for u64 {
+    fn from(lamp: &Lamp) -> u64 {
+        *lamp as u64
+    }
+}
+
+impl TryFrom<u64> for Flash {
+    type Error = ();
+
+    fn try_from(raw: u64) -> Result<Self, ()> {
+        match raw {
+            0 => Ok(Flash::Slow),
+            1 => Ok(Flash::Fast),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<&Flash> for u64 {
+    fn from(flash: &Flash) -> u64 {
+        *flash as u64
+    }
+}
+
+/// One 2 bit state per lamp, packed from the top of the byte down
+/// A raw 3 is not available and 2 is reserved by J1939-73, there's no error value
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq, Eq)]
+#[can_extract(crate = "crate", dlc = 1)]
+pub struct Lamps<T> {
+    #[can_extract(start_bit = 6, length = 2, codec = Enumerated<T>, not_available = 3, reserved = 2)]
+    pub malfunction: Signal<T>,
+    #[can_extract(start_bit = 4, length = 2, codec = Enumerated<T>, not_available = 3, reserved = 2)]
+    pub red_stop: Signal<T>,
+    #[can_extract(start_bit = 2, length = 2, codec = Enumerated<T>, not_available = 3, reserved = 2)]
+    pub amber_warning: Signal<T>,
+    #[can_extract(start_bit = 0, length = 2, codec = Enumerated<T>, not_available = 3, reserved = 2)]
+    pub protect: Signal<T>,
+}
+
+/// A diagnostic trouble code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dtc {
+    /// Suspect parameter number, the signal at fault
+    pub spn: u32,
+    /// Failure mode identifier, what's wrong with it
+    pub fmi: u8,
+    /// How many times the fault has gone active, 127 when not available
+    pub occurrence_count: u8,
+    /// Set by pre-1996 ECUs that lay the SPN out differently, which isn't decoded here
+    pub conversion_method: bool,
+}
+
+/// A DTC as it's laid out in the message, where the SPN's top 3 bits sit above the FMI
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq, Eq)]
+#[can_extract(crate = "crate", dlc = 4, arbitrary)]
+pub struct RawDtc {
+    #[can_extract(start_bit = 0, length = 16)]
+    pub spn_low: u16,
+    #[can_extract(start_bit = 16, length = 5)]
+    pub fmi: u8,
+    #[can_extract(start_bit = 21, length = 3)]
+    pub spn_high: u8,
+    #[can_extract(start_bit = 24, length = 7)]
+    pub occurrence_count: u8,
+    #[can_extract(start_bit = 31, length = 1)]
+    pub conversion_method: bool,
+}
+
+/// Active DTCs along with the state of the warning lamps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dm1 {
+    pub lamps: Lamps<Lamp>,
+    pub flash: Lamps<Flash>,
+    pub dtcs: Vec<Dtc>,
+}
+
+impl From<RawDtc> for Dtc {
+    fn from(raw: RawDtc) -> Self {
+        Self {
+            spn: raw.spn_low as u32 | (raw.spn_high as u32) << 16,
+            fmi: raw.fmi,
+            occurrence_count: raw.occurrence_count,
+            conversion_method: raw.conversion_method,
+        }
+    }
+}
+
+impl From<Dtc> for RawDtc {
+    fn from(dtc: Dtc) -> Self {
+        Self {
+            spn_low: dtc.spn as u16,
+            fmi: dtc.fmi,
+            spn_high: (dtc.spn >> 16) as u8,
+            occurrence_count: dtc.occurrence_count,
+            conversion_method: dtc.conversion_method,
+        }
+    }
+}
+
+impl Dm1 {
+    /// Lays the message back out - 8 bytes for up to one DTC, ready for TP beyond that
+    pub fn to_payload(&self) -> Result<Vec<u8>, Error> {
+        let mut payload = vec![self.lamps.to_socketcan()?[0], self.flash.to_socketcan()?[0]];
+        for dtc in &self.dtcs {
+            payload.extend_from_slice(&RawDtc::from(*dtc).to_socketcan()?[..DTC_LEN]);
+        }
+        if self.dtcs.is_empty() {
+            // No active faults is sent as an all zero DTC
+            payload.extend_from_slice(&[0; DTC_LEN]);
+        }
+        if payload.len() < 8 {
+            payload.resize(8, 0xFF);
+        }
+        Ok(payload)
+    }
+}
+
+impl CanDecodeSlice for Dm1 {
+    fn from_slice(payload: &[u8]) -> Result<Self, Error> {
+        let (lamps, dtcs) =
+            payload
+                .split_first_chunk::<LAMP_LEN>()
+                .ok_or(Error::FrameTooShort {
+                    expected: LAMP_LEN,
+                    actual: payload.len(),
+                })?;
+        // Padding and the all zero "no faults" DTC aren't faults
+        let dtcs = dtcs
+            .chunks_exact(DTC_LEN)
+            .filter(|dtc| dtc != &[0; DTC_LEN] && dtc != &[0xFF; DTC_LEN])
+            .map(|dtc| RawDtc::from_slice(dtc).map(Dtc::from))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            lamps: Lamps::from_slice(&lamps[..1])?,
+            flash: Lamps::from_slice(&lamps[1..])?,
+            dtcs,
+        })
+    }
+}
+
+impl CanDecode for Dm1 {
+    fn from_socketcan(frame: [u8; 8]) -> Result<Self, Error> {
+        Self::from_slice(&frame)
+    }
+}
+
+impl J1939Message for Dm1 {
+    const PGN: u32 = PGN_DM1;
+}
@@ -0,0 +1,381 @@
+//! J1939-21 transport protocol reassembly
+//!
+//! Payloads of 9 to 1785 bytes are announced on TP.CM - either as a BAM broadcast, or as an RTS
+//! to a single node that then paces the sender with CTS frames - and then sent 7 bytes at a time
+//! on TP.DT. The reassembler tracks a session per source and destination, answers the RTS
+//! sessions addressed to it, and hands back each payload once its last packet is in.
+
+use super::{Id, GLOBAL_ADDRESS};
+use crate::{CanDecodeSlice, Error};
+use core::time::Duration;
+use std::collections::BTreeMap;
+
+/// Connection management, announcing and pacing sessions
+pub const PGN_TP_CM: u32 = 0xEC00;
+/// Data transfer, 7 payload bytes after a sequence number
+pub const PGN_TP_DT: u32 = 0xEB00;
+
+/// Longest payload the transport protocol can carry
+pub const MAX_SIZE: usize = 1785;
+/// Longest gap allowed between data packets
+pub const T1: Duration = Duration::from_millis(750);
+/// Longest wait for data after sending a CTS
+pub const T2: Duration = Duration::from_millis(1250);
+
+const RTS: u8 = 16;
+const CTS: u8 = 17;
+const END_OF_MSG_ACK: u8 = 19;
+const BAM: u8 = 32;
+const ABORT: u8 = 255;
+
+/// Bytes of payload in each TP.DT frame
+const PACKET_LEN: usize = 7;
+
+/// A reassembled payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payload {
+    pub pgn: u32,
+    pub source: u8,
+    /// The node an RTS session was addressed to, None for BAM broadcasts
+    pub destination: Option<u8>,
+    pub data: Vec<u8>,
+}
+
+impl Payload {
+    pub fn decode<T: CanDecodeSlice>(&self) -> Result<T, Error> {
+        T::from_slice(&self.data)
+    }
+}
+
+/// Why a session was aborted, as carried in the TP.CM abort frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortReason {
+    /// Already in a session and can't support another
+    Busy,
+    /// Resources were needed for something else
+    Resources,
+    Timeout,
+    /// CTS received while a data transfer was in progress
+    CtsDuringTransfer,
+    RetransmitLimit,
+    UnexpectedData,
+    BadSequence,
+    DuplicateSequence,
+    /// The announced size was over 1785 bytes
+    TooLarge,
+    Other(u8),
+}
+
+impl From<u8> for AbortReason {
+    fn from(reason: u8) -> Self {
+        match reason {
+            1 => AbortReason::Busy,
+            2 => AbortReason::Resources,
+            3 => AbortReason::Timeout,
+            4 => AbortReason::CtsDuringTransfer,
+            5 => AbortReason::RetransmitLimit,
+            6 => AbortReason::UnexpectedData,
+            7 => AbortReason::BadSequence,
+            8 => AbortReason::DuplicateSequence,
+            9 => AbortReason::TooLarge,
+            other => AbortReason::Other(other),
+        }
+    }
+}
+
+impl From<AbortReason> for u8 {
+    fn from(reason: AbortReason) -> Self {
+        match reason {
+            AbortReason::Busy => 1,
+            AbortReason::Resources => 2,
+            AbortReason::Timeout => 3,
+            AbortReason::CtsDuringTransfer => 4,
+            AbortReason::RetransmitLimit => 5,
+            AbortReason::UnexpectedData => 6,
+            AbortReason::BadSequence => 7,
+            AbortReason::DuplicateSequence => 8,
+            AbortReason::TooLarge => 9,
+            AbortReason::Other(other) => other,
+        }
+    }
+}
+
+/// Something the caller needs to act on, out of a frame or a timeout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Complete(Payload),
+    /// A TP.CM frame to put on the bus - a CTS, end of message ack or abort for an RTS session
+    /// addressed to us
+    Send {
+        id: u32,
+        data: [u8; 8],
+    },
+    /// A session ended without a payload
+    Aborted {
+        pgn: u32,
+        source: u8,
+        destination: Option<u8>,
+        reason: AbortReason,
+    },
+}
+
+/// A session in progress, keyed by source and destination
+#[derive(Debug)]
+struct Session {
+    pgn: u32,
+    size: usize,
+    packets: u16,
+    /// Sequence number of the next packet, counting from 1
+    next: u16,
+    data: Vec<u8>,
+    /// Set when the session is addressed to us, so we send the CTS frames
+    answer: Option<Pacing>,
+    deadline: Duration,
+}
+
+/// CTS state for an RTS session we're answering
+#[derive(Debug)]
+struct Pacing {
+    max_per_cts: u8,
+    /// Last packet asked for by the latest CTS
+    block_end: u16,
+}
+
+/// Receive side of the transport protocol, for every session on the bus
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    address: Option<u8>,
+    sessions: BTreeMap<(u8, u8), Session>,
+}
+
+impl Reassembler {
+    /// `address` is our own, so RTS sessions to it get answered. None only listens
+    pub fn new(address: Option<u8>) -> Self {
+        Self {
+            address,
+            sessions: BTreeMap::new(),
+        }
+    }
+
+    /// Consumes a frame received at `now`, a timestamp from any monotonic clock
+    ///
+    /// Frames that aren't TP.CM or TP.DT are ignored. Sessions that timed out before `now` are
+    /// reported first.
+    pub fn push(&mut self, now: Duration, id: u32, frame: &[u8]) -> Result<Vec<Event>, Error> {
+        let id = Id::from_raw(id);
+        if id.pgn != PGN_TP_CM && id.pgn != PGN_TP_DT {
+            return Ok(Vec::new());
+        }
+        let frame: &[u8; 8] = frame
+            .get(..8)
+            .and_then(|frame| frame.try_into().ok())
+            .ok_or(Error::InvalidSlicingLength)?;
+
+        let mut events = self.poll(now);
+        let key = (id.source, id.destination.unwrap_or(GLOBAL_ADDRESS));
+        if id.pgn == PGN_TP_CM {
+            self.connection(now, key, frame, &mut events);
+        } else {
+            self.data(now, key, frame, &mut events);
+        }
+        Ok(events)
+    }
+
+    /// Drops the sessions that timed out before `now`
+    pub fn poll(&mut self, now: Duration) -> Vec<Event> {
+        let expired = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| session.deadline < now)
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+
+        let mut events = Vec::new();
+        for key in expired {
+            if let Some(session) = self.sessions.remove(&key) {
+                abort(key, session, AbortReason::Timeout, &mut events);
+            }
+        }
+        events
+    }
+
+    fn connection(
+        &mut self,
+        now: Duration,
+        key: (u8, u8),
+        frame: &[u8; 8],
+        events: &mut Vec<Event>,
+    ) {
+        let size = u16::from_le_bytes([frame[1], frame[2]]) as usize;
+        let packets = frame[3] as u16;
+        let pgn = u32::from_le_bytes([frame[5], frame[6], frame[7], 0]);
+        let announced = (9..=MAX_SIZE).contains(&size) && packets as usize == size.div_ceil(7);
+
+        match frame[0] {
+            BAM if key.1 == GLOBAL_ADDRESS && announced => {
+                // A new BAM from the same source replaces the old one
+                self.sessions.insert(
+                    key,
+                    Session {
+                        pgn,
+                        size,
+                        packets,
+                        next: 1,
+                        data: Vec::with_capacity(size),
+                        answer: None,
+                        deadline: now + T1,
+                    },
+                );
+            }
+            RTS if key.1 != GLOBAL_ADDRESS => {
+                let ours = Some(key.1) == self.address;
+                if !announced {
+                    if ours {
+                        events.push(send(key, cm_data(ABORT, AbortReason::TooLarge.into(), pgn)));
+                    }
+                    return;
+                }
+                let mut session = Session {
+                    pgn,
+                    size,
+                    packets,
+                    next: 1,
+                    data: Vec::with_capacity(size),
+                    answer: None,
+                    deadline: now + T1,
+                };
+                if ours {
+                    session.answer = Some(Pacing {
+                        max_per_cts: frame[4],
+                        block_end: 0,
+                    });
+                    events.push(clear_to_send(key, &mut session, now));
+                }
+                self.sessions.insert(key, session);
+            }
+            ABORT => {
+                // Either end can abort, so the session may be keyed the other way round
+                let reason = AbortReason::from(frame[1]);
+                for key in [key, (key.1, key.0)] {
+                    if let Some(session) = self.sessions.remove(&key) {
+                        events.push(Event::Aborted {
+                            pgn: session.pgn,
+                            source: key.0,
+                            destination: destination(key),
+                            reason,
+                        });
+                    }
+                }
+            }
+            // CTS and end of message acks are for the sending side
+            _ => {}
+        }
+    }
+
+    fn data(&mut self, now: Duration, key: (u8, u8), frame: &[u8; 8], events: &mut Vec<Event>) {
+        let Some(session) = self.sessions.get_mut(&key) else {
+            return;
+        };
+        let sequence = frame[0] as u16;
+        if sequence < session.next {
+            // A retransmission of something we already have
+            return;
+        }
+        if sequence > session.next {
+            if let Some(session) = self.sessions.remove(&key) {
+                abort(key, session, AbortReason::BadSequence, events);
+            }
+            return;
+        }
+
+        let remaining = session.size - session.data.len();
+        session
+            .data
+            .extend_from_slice(&frame[1..1 + remaining.min(PACKET_LEN)]);
+        session.next += 1;
+        session.deadline = now + T1;
+
+        if session.data.len() == session.size {
+            if let Some(session) = self.sessions.remove(&key) {
+                if session.answer.is_some() {
+                    let [low, high] = (session.size as u16).to_le_bytes();
+                    let mut data = cm_data(END_OF_MSG_ACK, low, session.pgn);
+                    data[2] = high;
+                    data[3] = session.packets as u8;
+                    events.push(send(key, data));
+                }
+                events.push(Event::Complete(Payload {
+                    pgn: session.pgn,
+                    source: key.0,
+                    destination: destination(key),
+                    data: session.data,
+                }));
+            }
+        } else if session
+            .answer
+            .as_ref()
+            .is_some_and(|pacing| pacing.block_end == sequence)
+        {
+            events.push(clear_to_send(key, session, now));
+        }
+    }
+}
+
+/// Asks for the next block of packets, as many as the sender allows
+fn clear_to_send(key: (u8, u8), session: &mut Session, now: Duration) -> Event {
+    let remaining = session.packets - session.next + 1;
+    let count = match &session.answer {
+        Some(pacing) => remaining.min(pacing.max_per_cts as u16),
+        None => remaining,
+    };
+    if let Some(pacing) = session.answer.as_mut() {
+        pacing.block_end = session.next + count - 1;
+    }
+    session.deadline = now + T2;
+
+    let mut data = cm_data(CTS, count as u8, session.pgn);
+    data[2] = session.next as u8;
+    send(key, data)
+}
+
+/// Reports a dropped session, telling the sender too when it was addressed to us
+fn abort(key: (u8, u8), session: Session, reason: AbortReason, events: &mut Vec<Event>) {
+    if session.answer.is_some() {
+        events.push(send(key, cm_data(ABORT, reason.into(), session.pgn)));
+    }
+    events.push(Event::Aborted {
+        pgn: session.pgn,
+        source: key.0,
+        destination: destination(key),
+        reason,
+    });
+}
+
+/// A TP.CM frame with its control byte, first argument byte and the PGN filled in
+fn cm_data(control: u8, argument: u8, pgn: u32) -> [u8; 8] {
+    let [pgn_low, pgn_mid, pgn_high, _] = pgn.to_le_bytes();
+    [
+        control, argument, 0xFF, 0xFF, 0xFF, pgn_low, pgn_mid, pgn_high,
+    ]
+}
+
+/// A TP.CM frame from us (the session's destination) back to the session's source
+fn send(key: (u8, u8), data: [u8; 8]) -> Event {
+    let id = Id {
+        priority: 7,
+        pgn: PGN_TP_CM,
+        source: key.1,
+        destination: Some(key.0),
+    };
+    Event::Send {
+        id: id.to_raw(),
+        data,
+    }
+}
+
+fn destination(key: (u8, u8)) -> Option<u8> {
+    match key.1 {
+        GLOBAL_ADDRESS => None,
+        destination => Some(destination),
+    }
+}
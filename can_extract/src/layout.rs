@@ -0,0 +1,55 @@
+//! Static layout introspection for derived messages
+//!
+//! The derive records where each member lives in the frame, so tooling (docs, DBC export,
+//! loggers) can describe a message without decoding one.
+
+use crate::ByteOrder;
+
+/// Linear scaling from a raw value, `physical = raw * factor + offset`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scaling {
+    pub factor: f64,
+    pub offset: f64,
+}
+
+impl Scaling {
+    /// The raw value is the physical value
+    pub const IDENTITY: Scaling = Scaling {
+        factor: 1.0,
+        offset: 0.0,
+    };
+}
+
+/// Where a single member lives in the frame, and how to read it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignalInfo {
+    pub name: &'static str,
+    /// DBC convention - the LSB for little endian, the MSB for big endian
+    pub start_bit: u16,
+    /// Length in bits
+    pub length: u16,
+    pub byte_order: ByteOrder,
+    /// Whether the raw value is two's complement
+    pub signed: bool,
+    /// None when a custom decoder sits between the raw and physical value
+    pub scaling: Option<Scaling>,
+    pub unit: Option<&'static str>,
+    /// The Rust type of the struct member
+    pub type_name: &'static str,
+    /// J1939 suspect parameter number, when declared with `spn = ..`
+    pub spn: Option<u32>,
+    /// Physical range, as DBC's `[min|max]`, when declared with `min = ..` and `max = ..`
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Implemented by the derive, describing the message and all of its signals
+pub trait CanLayout {
+    /// The CAN ID, when declared with `id = ..`
+    const ID: Option<u32>;
+    /// The `name = ".."` declared, or the struct name
+    const NAME: &'static str;
+    /// The data length, when declared with `dlc = ..`
+    const DLC: Option<u8>;
+    const SIGNALS: &'static [SignalInfo];
+}
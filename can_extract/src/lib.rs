@@ -1,5 +1,31 @@
 pub use socketcan;
 
+#[cfg(feature = "proptest")]
+pub use proptest;
+#[cfg(feature = "serde")]
+pub use serde;
+#[cfg(feature = "uom")]
+pub use uom;
+
+pub mod canopen;
+pub mod codec;
+pub mod e2e;
+pub mod field;
+pub mod isotp;
+pub mod j1939;
+pub mod layout;
+pub mod nmea2000;
+pub mod obd2;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod signal;
+pub mod uds;
+
+pub use codec::FieldCodec;
+pub use field::CanField;
+pub use layout::{CanLayout, Scaling, SignalInfo};
+pub use signal::Signal;
+
 /// Really simple error cases
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -9,6 +35,33 @@ pub enum Error {
     InvalidSlicingLength,
     #[error("Invalid bytes conversion")]
     InvalidBytesConversion,
+    #[error("Value doesn't fit in the signal")]
+    ValueOutOfRange,
+    #[error("Frame doesn't fit the transport protocol state")]
+    UnexpectedFrame,
+    #[error("Transport protocol is already sending")]
+    TransportBusy,
+    #[error("Transport protocol timed out")]
+    TransportTimeout,
+    #[error("Payload is too long for the receiver")]
+    TransportOverflow,
+    #[error("Object dictionary file is malformed, or maps something a PDO can't carry")]
+    InvalidObjectDictionary,
+    #[error("Frame too short, expected at least {expected} bytes but got {actual}")]
+    FrameTooShort { expected: usize, actual: usize },
+    #[error("Frame length doesn't match the DLC, expected {expected} bytes but got {actual}")]
+    DlcMismatch { expected: usize, actual: usize },
+    #[error("Signal {signal} is {value}, outside its physical range")]
+    PhysicalOutOfRange { signal: &'static str, value: f64 },
+}
+
+/// Byte order of a member in the frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// Intel
+    LittleEndian,
+    /// Motorola
+    BigEndian,
 }
 
 /// Define a means to consume an 8 byte CAN frame and give us a type
@@ -16,8 +69,97 @@ pub trait CanDecode: Sized {
     fn from_socketcan(frame: [u8; 8]) -> Result<Self, Error>;
 }
 
+/// Like CanDecode, but for payloads of any length - e.g. short frames, or those reassembled by a
+/// transport protocol
+pub trait CanDecodeSlice: Sized {
+    /// Fewest bytes holding every member
+    const MIN_LEN: usize = 0;
+    /// Exact payload length, when the type declares one with `dlc = ..` - the derive fails to
+    /// compile if that is shorter than MIN_LEN
+    const DLC: Option<usize> = None;
+
+    fn from_slice(payload: &[u8]) -> Result<Self, Error>;
+}
+
+/// The inverse of CanDecode - lay a type back out into an 8 byte CAN frame
+pub trait CanEncode {
+    fn to_socketcan(&self) -> Result<[u8; 8], Error>;
+}
+
+/// The inverse of CanDecodeSlice - lay a type out into a payload of its `dlc`, or else just long
+/// enough for every member
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be encoded into a payload",
+    label = "needs `#[derive(CanEncode)]`",
+    note = "`serialize_raw` members read their raw value back out of the encoded payload"
+)]
+pub trait CanEncodeSlice {
+    fn to_vec(&self) -> Result<Vec<u8>, Error>;
+}
+
+/// Implemented by the derive, flagging members decoded outside their declared `min` and `max`
+/// Members with `out_of_range = "flag"` decode as sent, so check this before trusting them
+pub trait CanValidate {
+    /// Bit n is set when the member described by `SIGNALS[n]` is out of range
+    fn out_of_range(&self) -> u64;
+}
+
+/// Property tests a type deriving CanDecode and CanEncode, tagged `#[can_extract(arbitrary)]` so it
+/// derives Arbitrary too
+/// Asserts that every generated value survives an encode then decode, and that decoding random
+/// frames never panics. Call it from inside a #[test]
+#[cfg(feature = "proptest")]
+#[macro_export]
+macro_rules! assert_round_trip {
+    ($ty:ty) => {
+        $crate::proptest::proptest!(|(value in $crate::proptest::arbitrary::any::<$ty>())| {
+            let frame = $crate::CanEncode::to_socketcan(&value)?;
+            let decoded = <$ty as $crate::CanDecode>::from_socketcan(frame)?;
+            $crate::proptest::prop_assert_eq!(decoded, value);
+        });
+        $crate::proptest::proptest!(|(frame in $crate::proptest::arbitrary::any::<[u8; 8]>())| {
+            let _ = <$ty as $crate::CanDecode>::from_socketcan(frame);
+        });
+    };
+}
+
 pub mod helper {
-    use crate::Error;
+    use crate::{ByteOrder, Error};
+
+    /// Checks a payload against CanDecodeSlice::MIN_LEN and CanDecodeSlice::DLC
+    pub fn check_len(payload: &[u8], min_len: usize, dlc: Option<usize>) -> Result<(), Error> {
+        let actual = payload.len();
+        let expected = dlc.unwrap_or(0).max(min_len);
+        if actual < expected {
+            return Err(Error::FrameTooShort { expected, actual });
+        }
+        match dlc {
+            Some(expected) if actual != expected => Err(Error::DlcMismatch { expected, actual }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The bound a physical value falls outside of, if any
+    pub fn out_of_range(value: f64, min: Option<f64>, max: Option<f64>) -> Option<f64> {
+        match (min, max) {
+            (Some(min), _) if value < min => Some(min),
+            (_, Some(max)) if value > max => Some(max),
+            _ => None,
+        }
+    }
+
+    /// Fails a decoded value outside its member's `min` and `max`
+    pub fn check_range(
+        signal: &'static str,
+        value: f64,
+        min: Option<f64>,
+        max: Option<f64>,
+    ) -> Result<(), Error> {
+        match out_of_range(value, min, max) {
+            Some(_) => Err(Error::PhysicalOutOfRange { signal, value }),
+            None => Ok(()),
+        }
+    }
 
     /// Advances a counting token and returns the next bit of the slice
     /// Note that we don't use [] as its panic-able
@@ -60,4 +202,164 @@ pub mod helper {
             .ok_or_else(|| Error::InvalidSlicingLength)?;
         Ok(slice)
     }
+
+    /// Mutable version of extract_offset, for encoding
+    pub fn extract_offset_mut<T: Sized>(
+        offset: usize,
+        frame: &mut [u8],
+    ) -> Result<&mut [u8], Error> {
+        let extract_bytes = std::mem::size_of::<T>();
+        extract_offset_mut_by(offset, frame, extract_bytes)
+    }
+
+    pub fn extract_offset_mut_by(
+        offset: usize,
+        frame: &mut [u8],
+        extract_bytes: usize,
+    ) -> Result<&mut [u8], Error> {
+        let next = offset + extract_bytes;
+        frame
+            .get_mut(offset..next)
+            .ok_or(Error::InvalidSlicingLength)
+    }
+
+    /// Copies bytes into an extracted slice
+    /// Note that we don't use copy_from_slice as its panic-able
+    pub fn insert_bytes(slice: &mut [u8], bytes: &[u8]) -> Result<(), Error> {
+        if slice.len() != bytes.len() {
+            return Err(Error::InvalidBytesConversion);
+        }
+        slice.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Reads extracted bytes as an unsigned integer, for reporting raw signal values
+    pub fn raw_value(bytes: &[u8], big_endian: bool) -> u64 {
+        let fold = |raw: u64, byte: &u8| (raw << 8) | *byte as u64;
+        if big_endian {
+            bytes.iter().fold(0, fold)
+        } else {
+            bytes.iter().rev().fold(0, fold)
+        }
+    }
+
+    /// Writes a raw value into extracted bytes, the inverse of raw_value
+    pub fn insert_raw(bytes: &mut [u8], raw: u64, big_endian: bool) -> Result<(), Error> {
+        let width = bytes.len() * 8;
+        if width > 64 || (width < 64 && raw >> width != 0) {
+            return Err(Error::ValueOutOfRange);
+        }
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            *byte = (raw >> (index * 8)) as u8;
+        }
+        if big_endian {
+            bytes.reverse();
+        }
+        Ok(())
+    }
+
+    /// Frame position (byte, bit) of the `index`th bit of a signal, counting up from its LSB
+    /// Intel signals start at their LSB and count up through the frame. Motorola signals start
+    /// at their MSB and count down, following the DBC sawtooth where bit 0 of a byte is followed
+    /// by bit 7 of the next byte
+    fn bit_position(start_bit: u16, length: u16, index: u16, byte_order: ByteOrder) -> (usize, u8) {
+        match byte_order {
+            ByteOrder::LittleEndian => {
+                let bit = start_bit as usize + index as usize;
+                (bit / 8, (bit % 8) as u8)
+            }
+            ByteOrder::BigEndian => {
+                // Straighten the sawtooth out, so bits run MSB first through the frame
+                let msb = (start_bit / 8) * 8 + (7 - start_bit % 8);
+                let bit = msb as usize + (length - 1 - index) as usize;
+                (bit / 8, 7 - (bit % 8) as u8)
+            }
+        }
+    }
+
+    /// Extracts a signal of up to 64 bits, using DBC start bit semantics
+    pub fn extract_bits(
+        frame: &[u8],
+        start_bit: u16,
+        length: u16,
+        byte_order: ByteOrder,
+    ) -> Result<u64, Error> {
+        if length == 0 || length > 64 {
+            return Err(Error::InvalidSlicingLength);
+        }
+        let mut raw = 0;
+        for index in 0..length {
+            let (byte, bit) = bit_position(start_bit, length, index, byte_order);
+            let byte = frame.get(byte).ok_or(Error::InvalidSlicingLength)?;
+            raw |= (((byte >> bit) & 1) as u64) << index;
+        }
+        Ok(raw)
+    }
+
+    /// Inserts the low `length` bits of `raw`, the inverse of extract_bits
+    pub fn insert_bits(
+        frame: &mut [u8],
+        start_bit: u16,
+        length: u16,
+        byte_order: ByteOrder,
+        raw: u64,
+    ) -> Result<(), Error> {
+        if length == 0 || length > 64 {
+            return Err(Error::InvalidSlicingLength);
+        }
+        for index in 0..length {
+            let (byte, bit) = bit_position(start_bit, length, index, byte_order);
+            let byte = frame.get_mut(byte).ok_or(Error::InvalidSlicingLength)?;
+            *byte &= !(1 << bit);
+            *byte |= (((raw >> index) & 1) as u8) << bit;
+        }
+        Ok(())
+    }
+
+    /// Conversion between a member and the raw bits of a signal
+    pub trait RawBits: Sized {
+        /// Builds the member from the low `length` bits of `raw`
+        fn from_raw(raw: u64, length: u16) -> Self;
+        fn to_raw(&self) -> u64;
+    }
+
+    macro_rules! impl_raw_bits_unsigned {
+        ($($ty:ty),*) => {$(
+            impl RawBits for $ty {
+                fn from_raw(raw: u64, _length: u16) -> Self {
+                    raw as $ty
+                }
+                fn to_raw(&self) -> u64 {
+                    *self as u64
+                }
+            }
+        )*};
+    }
+
+    macro_rules! impl_raw_bits_signed {
+        ($($ty:ty),*) => {$(
+            impl RawBits for $ty {
+                fn from_raw(raw: u64, length: u16) -> Self {
+                    // Shift the sign bit up to the top, then arithmetic shift back down
+                    let shift = 64 - length.clamp(1, 64) as u32;
+                    (((raw << shift) as i64) >> shift) as $ty
+                }
+                fn to_raw(&self) -> u64 {
+                    *self as u64
+                }
+            }
+        )*};
+    }
+
+    impl_raw_bits_unsigned!(u8, u16, u32, u64);
+    impl_raw_bits_signed!(i8, i16, i32, i64);
+
+    impl RawBits for bool {
+        fn from_raw(raw: u64, _length: u16) -> Self {
+            raw != 0
+        }
+        fn to_raw(&self) -> u64 {
+            *self as u64
+        }
+    }
 }
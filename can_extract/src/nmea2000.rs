@@ -0,0 +1,261 @@
+//! NMEA 2000, J1939 addressing with fast-packet framing for longer PGNs
+//!
+//! A fast packet carries up to 223 bytes in as many as 32 frames. Each frame starts with a
+//! sequence counter in its top 3 bits, so back to back messages from one sender can be told
+//! apart, and a frame counter in its low 5 bits. Frame 0 gives the length and the first 6
+//! bytes, the rest carry 7 bytes each.
+//!
+//! Nothing in the frame says whether a PGN is fast-packet, so the [`Reassembler`] is told which
+//! ones are. Messages are declared with `pgn = ..` like any J1939 message, plus the
+//! `fast_packet` tag to decode them from the reassembled payload.
+
+use crate::codec::{Scaled, ScaledSigned};
+use crate::j1939::tp::Payload;
+use crate::j1939::{Id, J1939Message};
+use crate::{CanDecodeSlice, Error, Signal};
+use core::time::Duration;
+use proc_macro_derive_crate::{CanDecode, CanEncode};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Longest payload a fast packet can carry
+pub const MAX_SIZE: usize = 223;
+/// Longest gap allowed between the frames of a fast packet
+pub const TIMEOUT: Duration = Duration::from_millis(750);
+
+/// Fast-packet PGNs among the messages provided here
+pub const FAST_PACKET_PGNS: &[u32] = &[PGN_ENGINE_PARAMETERS_DYNAMIC];
+
+pub const PGN_VESSEL_HEADING: u32 = 127250;
+pub const PGN_ENGINE_PARAMETERS_RAPID: u32 = 127488;
+pub const PGN_ENGINE_PARAMETERS_DYNAMIC: u32 = 127489;
+pub const PGN_POSITION_RAPID: u32 = 129025;
+
+/// Bytes of payload in the first frame, after the counters and the length
+const FIRST_FRAME_LEN: usize = 6;
+/// Bytes of payload in every frame after the first
+const FRAME_LEN: usize = 7;
+
+/// Implemented by the derive for messages tagged `fast_packet`
+pub trait FastPacket: J1939Message + CanDecodeSlice {}
+
+/// A fast packet in progress, keyed by source and PGN
+#[derive(Debug)]
+struct Session {
+    sequence: u8,
+    size: usize,
+    /// Frame counter of the next frame
+    next: u8,
+    data: Vec<u8>,
+    deadline: Duration,
+}
+
+/// Receive side of NMEA 2000, handing back every message as a payload
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    fast_packet: BTreeSet<u32>,
+    sessions: BTreeMap<(u8, u32), Session>,
+}
+
+impl Reassembler {
+    /// `fast_packet` lists the PGNs to reassemble, everything else is a single frame
+    pub fn new(fast_packet: impl IntoIterator<Item = u32>) -> Self {
+        Self {
+            fast_packet: fast_packet.into_iter().collect(),
+            sessions: BTreeMap::new(),
+        }
+    }
+
+    /// Reassembles a fast-packet message too
+    pub fn register<T: FastPacket>(&mut self) {
+        self.fast_packet.insert(T::PGN);
+    }
+
+    /// Consumes a frame received at `now`, a timestamp from any monotonic clock
+    ///
+    /// Single frame messages come straight back. Fast packets come back once their last frame
+    /// is in, and are dropped when a frame goes missing.
+    pub fn push(&mut self, now: Duration, id: u32, frame: &[u8]) -> Result<Option<Payload>, Error> {
+        self.poll(now);
+        let id = Id::from_raw(id);
+        if !self.fast_packet.contains(&id.pgn) {
+            return Ok(Some(Payload {
+                pgn: id.pgn,
+                source: id.source,
+                destination: id.destination,
+                data: frame.to_vec(),
+            }));
+        }
+
+        let (&counters, data) = frame.split_first().ok_or(Error::InvalidSlicingLength)?;
+        let sequence = counters >> 5;
+        let counter = counters & 0x1F;
+        let key = (id.source, id.pgn);
+
+        let payload = |data| Payload {
+            pgn: id.pgn,
+            source: id.source,
+            destination: id.destination,
+            data,
+        };
+
+        if counter == 0 {
+            // A new message from the same source replaces one that never finished
+            let (&size, data) = data.split_first().ok_or(Error::InvalidSlicingLength)?;
+            let size = size as usize;
+            self.sessions.remove(&key);
+            if size > MAX_SIZE {
+                return Err(Error::TransportOverflow);
+            }
+            let mut session = Session {
+                sequence,
+                size,
+                next: 1,
+                data: Vec::with_capacity(size),
+                deadline: now + TIMEOUT,
+            };
+            session
+                .data
+                .extend_from_slice(&data[..data.len().min(size).min(FIRST_FRAME_LEN)]);
+            if session.data.len() == size {
+                return Ok(Some(payload(session.data)));
+            }
+            self.sessions.insert(key, session);
+            return Ok(None);
+        }
+
+        let Some(session) = self.sessions.get_mut(&key) else {
+            // Joined part way through
+            return Ok(None);
+        };
+        if session.sequence != sequence || session.next != counter {
+            self.sessions.remove(&key);
+            return Ok(None);
+        }
+        let remaining = session.size - session.data.len();
+        session
+            .data
+            .extend_from_slice(&data[..data.len().min(remaining).min(FRAME_LEN)]);
+        session.next += 1;
+        session.deadline = now + TIMEOUT;
+
+        if session.data.len() < session.size {
+            return Ok(None);
+        }
+        Ok(self
+            .sessions
+            .remove(&key)
+            .map(|session| payload(session.data)))
+    }
+
+    /// Drops the fast packets that stalled before `now`
+    pub fn poll(&mut self, now: Duration) {
+        self.sessions.retain(|_, session| session.deadline >= now);
+    }
+}
+
+/// Lays a payload out as fast-packet frames, numbered with `sequence`
+pub fn fast_packet_frames(sequence: u8, payload: &[u8]) -> Result<Vec<[u8; 8]>, Error> {
+    if payload.len() > MAX_SIZE {
+        return Err(Error::TransportOverflow);
+    }
+    let sequence = (sequence & 0x07) << 5;
+    let split = payload.len().min(FIRST_FRAME_LEN);
+    let mut first = [0xFF; 8];
+    first[0] = sequence;
+    first[1] = payload.len() as u8;
+    first[2..2 + split].copy_from_slice(&payload[..split]);
+
+    let mut frames = vec![first];
+    for (counter, chunk) in payload[split..].chunks(FRAME_LEN).enumerate() {
+        let mut frame = [0xFF; 8];
+        frame[0] = sequence | (counter as u8 + 1);
+        frame[1..1 + chunk.len()].copy_from_slice(chunk);
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+/// PGN 129025, sent up to 10 times a second
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pgn = 129025, name = "Position, Rapid Update")]
+pub struct PositionRapidUpdate {
+    #[can_extract(offset = 0, extract = 4, codec = ScaledSigned<1, 10_000_000>, unit = "deg", not_available = 0x7FFF_FFFF)]
+    pub latitude: Signal<f64>,
+    #[can_extract(offset = 4, extract = 4, codec = ScaledSigned<1, 10_000_000>, unit = "deg", not_available = 0x7FFF_FFFF)]
+    pub longitude: Signal<f64>,
+}
+
+/// PGN 127250
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pgn = 127250, name = "Vessel Heading")]
+pub struct VesselHeading {
+    /// Ties together messages taken at the same moment
+    #[can_extract(offset = 0)]
+    pub sid: u8,
+    #[can_extract(offset = 1, extract = 2, codec = Scaled<1, 10_000>, unit = "rad", not_available = 0xFFFF)]
+    pub heading: Signal<f32>,
+    #[can_extract(offset = 3, extract = 2, codec = ScaledSigned<1, 10_000>, unit = "rad", not_available = 0x7FFF)]
+    pub deviation: Signal<f32>,
+    #[can_extract(offset = 5, extract = 2, codec = ScaledSigned<1, 10_000>, unit = "rad", not_available = 0x7FFF)]
+    pub variation: Signal<f32>,
+    /// 0 for true north, 1 for magnetic
+    #[can_extract(start_bit = 56, length = 2)]
+    pub reference: Signal<u8>,
+}
+
+/// PGN 127488, sent up to 10 times a second
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(
+    crate = "crate",
+    pgn = 127488,
+    name = "Engine Parameters, Rapid Update"
+)]
+pub struct EngineParametersRapid {
+    /// 0 for a single or the port engine, 1 for starboard
+    #[can_extract(offset = 0)]
+    pub instance: u8,
+    #[can_extract(offset = 1, extract = 2, codec = Scaled<1, 4>, unit = "rpm", not_available = 0xFFFF)]
+    pub speed: Signal<f32>,
+    #[can_extract(offset = 3, extract = 2, codec = Scaled<1, 10>, unit = "kPa", not_available = 0xFFFF)]
+    pub boost_pressure: Signal<f32>,
+    #[can_extract(offset = 5, unit = "%", not_available = 0x7F)]
+    pub tilt_trim: Signal<i8>,
+}
+
+/// PGN 127489, a 26 byte fast packet decoded from the reassembled [`Payload`]
+#[derive(CanDecode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(
+    crate = "crate",
+    pgn = 127489,
+    fast_packet,
+    name = "Engine Parameters, Dynamic"
+)]
+pub struct EngineParametersDynamic {
+    #[can_extract(offset = 0)]
+    pub instance: u8,
+    #[can_extract(offset = 1, extract = 2, codec = Scaled<1, 10>, unit = "kPa", not_available = 0xFFFF)]
+    pub oil_pressure: Signal<f32>,
+    #[can_extract(offset = 3, extract = 2, codec = Scaled<1, 10>, unit = "K", not_available = 0xFFFF)]
+    pub oil_temperature: Signal<f32>,
+    #[can_extract(offset = 5, extract = 2, codec = Scaled<1, 100>, unit = "K", not_available = 0xFFFF)]
+    pub coolant_temperature: Signal<f32>,
+    #[can_extract(offset = 7, extract = 2, codec = ScaledSigned<1, 100>, unit = "V", not_available = 0x7FFF)]
+    pub alternator_potential: Signal<f32>,
+    #[can_extract(offset = 9, extract = 2, codec = ScaledSigned<1, 10>, unit = "L/h", not_available = 0x7FFF)]
+    pub fuel_rate: Signal<f32>,
+    #[can_extract(offset = 11, unit = "s", not_available = 0xFFFF_FFFF)]
+    pub total_engine_hours: Signal<u32>,
+    #[can_extract(offset = 15, extract = 2, codec = Scaled<1, 10>, unit = "kPa", not_available = 0xFFFF)]
+    pub coolant_pressure: Signal<f32>,
+    #[can_extract(offset = 17, extract = 2, codec = Scaled<1, 1>, unit = "kPa", not_available = 0xFFFF)]
+    pub fuel_pressure: Signal<f32>,
+    /// Alarm and warning flags
+    #[can_extract(offset = 20)]
+    pub discrete_status_1: u16,
+    #[can_extract(offset = 22)]
+    pub discrete_status_2: u16,
+    #[can_extract(offset = 24, unit = "%", not_available = 0x7F)]
+    pub load: Signal<i8>,
+    #[can_extract(offset = 25, unit = "%", not_available = 0x7F)]
+    pub torque: Signal<i8>,
+}
@@ -0,0 +1,325 @@
+//! SAE J1979 / ISO 15031-5 OBD-II, service 01 (current data) and 02 (freeze frame data)
+//!
+//! A scan tool asks every emissions ECU at once on the functional address 0x7DF, and each one
+//! answers from 0x7E8 to 0x7EF. Requests and responses are ISO-TP payloads: the service, the
+//! PID, a freeze frame number for service 02, then the PID's data bytes A, B, C, D..
+//!
+//! Each PID is a struct deriving CanDecode over its data bytes, with a `pid = ..` container
+//! attribute, pulled out of a response with [`Response::decode`]. The standard PIDs below carry
+//! the J1979 formulas as codecs, and manufacturer PIDs are declared the same way.
+
+use crate::codec::Scaled;
+use crate::{isotp, CanDecodeSlice, Error};
+use core::ops::RangeInclusive;
+use proc_macro_derive_crate::{CanDecode, CanEncode};
+
+/// Every emissions ECU listens on this ID
+pub const FUNCTIONAL_ID: u32 = 0x7DF;
+/// ECUs answer on 0x7E8 plus their number, and take physical requests on 0x7E0 plus it
+pub const RESPONSE_IDS: RangeInclusive<u32> = 0x7E8..=0x7EF;
+
+pub const SHOW_CURRENT_DATA: u8 = 0x01;
+pub const SHOW_FREEZE_FRAME_DATA: u8 = 0x02;
+
+/// Added to a service for its positive response
+const POSITIVE_RESPONSE_OFFSET: u8 = 0x40;
+/// PIDs 0x00, 0x20, 0x40.. report which of the next 32 PIDs are supported
+const SUPPORTED_PIDS_STRIDE: u8 = 0x20;
+
+/// Implemented by the derive for PIDs declaring a `pid`
+pub trait Pid: CanDecodeSlice {
+    const PID: u8;
+
+    /// Whether a response for `pid` carries this PID
+    fn matches(pid: u8) -> bool {
+        pid == Self::PID
+    }
+}
+
+/// The physical request ID of the ECU that answered on `response_id`
+pub fn request_id(response_id: u32) -> Option<u32> {
+    RESPONSE_IDS.contains(&response_id).then(|| response_id - 8)
+}
+
+/// A service 01 or 02 request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Request {
+    CurrentData {
+        pid: u8,
+    },
+    /// Data captured when a DTC was stored, frame 0 being the one J1979 requires
+    FreezeFrame {
+        pid: u8,
+        frame: u8,
+    },
+}
+
+impl Request {
+    pub fn current<P: Pid>() -> Self {
+        Request::CurrentData { pid: P::PID }
+    }
+
+    pub fn freeze_frame<P: Pid>(frame: u8) -> Self {
+        Request::FreezeFrame { pid: P::PID, frame }
+    }
+
+    pub fn service(&self) -> u8 {
+        match self {
+            Request::CurrentData { .. } => SHOW_CURRENT_DATA,
+            Request::FreezeFrame { .. } => SHOW_FREEZE_FRAME_DATA,
+        }
+    }
+
+    /// Lays the request out as an ISO-TP payload
+    pub fn to_payload(&self) -> Vec<u8> {
+        match *self {
+            Request::CurrentData { pid } => vec![SHOW_CURRENT_DATA, pid],
+            Request::FreezeFrame { pid, frame } => vec![SHOW_FREEZE_FRAME_DATA, pid, frame],
+        }
+    }
+
+    /// The single frame to send on [`FUNCTIONAL_ID`], padded like the ISO-TP default
+    pub fn to_frame(&self) -> [u8; 8] {
+        let payload = self.to_payload();
+        let mut frame = [isotp::Config::default().padding; 8];
+        frame[0] = payload.len() as u8;
+        frame[1..=payload.len()].copy_from_slice(&payload);
+        frame
+    }
+}
+
+/// A positive service 01 or 02 response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub service: u8,
+    pub pid: u8,
+    /// The freeze frame number, for service 02
+    pub frame: Option<u8>,
+    /// Data bytes A, B, C..
+    pub data: Vec<u8>,
+}
+
+impl Response {
+    /// Decodes a response carried in a single frame, which covers every standard PID
+    pub fn from_frame(frame: [u8; 8]) -> Result<Self, Error> {
+        // Anything but a single frame PCI is a longer response, needing isotp::Receiver
+        if frame[0] >> 4 != 0 {
+            return Err(Error::UnexpectedFrame);
+        }
+        let len = (frame[0] & 0x0F) as usize;
+        Self::from_slice(frame.get(1..=len).ok_or(Error::InvalidSlicingLength)?)
+    }
+
+    /// Decodes the data bytes through a PID's derived layout
+    ///
+    /// Returns None when the response is for another PID.
+    pub fn decode<P: Pid>(&self) -> Result<Option<P>, Error> {
+        if !P::matches(self.pid) {
+            return Ok(None);
+        }
+        P::from_slice(&self.data).map(Some)
+    }
+
+    /// Lays the response out as an ISO-TP payload
+    pub fn to_payload(&self) -> Vec<u8> {
+        let mut payload = vec![
+            self.service.wrapping_add(POSITIVE_RESPONSE_OFFSET),
+            self.pid,
+        ];
+        payload.extend(self.frame);
+        payload.extend_from_slice(&self.data);
+        payload
+    }
+}
+
+impl CanDecodeSlice for Response {
+    fn from_slice(payload: &[u8]) -> Result<Self, Error> {
+        let [sid, pid, rest @ ..] = payload else {
+            return Err(Error::InvalidSlicingLength);
+        };
+        let (service, frame, data) = match sid.wrapping_sub(POSITIVE_RESPONSE_OFFSET) {
+            SHOW_CURRENT_DATA => (SHOW_CURRENT_DATA, None, rest),
+            SHOW_FREEZE_FRAME_DATA => {
+                let (frame, data) = rest.split_first().ok_or(Error::InvalidSlicingLength)?;
+                (SHOW_FREEZE_FRAME_DATA, Some(*frame), data)
+            }
+            // Negative responses and the other services aren't covered
+            _ => return Err(Error::UnexpectedFrame),
+        };
+        Ok(Self {
+            service,
+            pid: *pid,
+            frame,
+            data: data.to_vec(),
+        })
+    }
+}
+
+/// PIDs 0x00, 0x20, 0x40.. - bit 31 down to bit 0 flag the 32 PIDs after the one asked for
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq, Eq)]
+#[can_extract(crate = "crate")]
+pub struct SupportedPids {
+    #[can_extract(offset = 0, use_big_endian)]
+    pub bitmap: u32,
+}
+
+impl SupportedPids {
+    /// Whether `pid` is flagged, where `base` is the PID this bitmap was read from
+    pub fn supports(&self, base: u8, pid: u8) -> bool {
+        match pid.checked_sub(base) {
+            Some(bit @ 1..=32) => self.bitmap & (1 << (32 - bit as u32)) != 0,
+            _ => false,
+        }
+    }
+
+    /// The flagged PIDs, in order
+    pub fn pids(&self, base: u8) -> impl Iterator<Item = u8> + '_ {
+        (1..=32)
+            .filter_map(move |bit| base.checked_add(bit))
+            .filter(move |pid| self.supports(base, *pid))
+    }
+}
+
+impl Pid for SupportedPids {
+    const PID: u8 = 0x00;
+
+    fn matches(pid: u8) -> bool {
+        pid.is_multiple_of(SUPPORTED_PIDS_STRIDE)
+    }
+}
+
+/// PID 0x04, `100 / 255 * A`
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x04)]
+pub struct EngineLoad {
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<100, 255>, unit = "%")]
+    pub load: f32,
+}
+
+/// PID 0x05, `A - 40`
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x05)]
+pub struct CoolantTemperature {
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<1, 1, { -40 }>, unit = "degC")]
+    pub temperature: f32,
+}
+
+/// PID 0x06, `100 / 128 * A - 100`, negative running rich and positive running lean
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x06)]
+pub struct ShortTermFuelTrimBank1 {
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<100, 128, { -100 }>, unit = "%")]
+    pub trim: f32,
+}
+
+/// PID 0x07, the same as the short term trim
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x07)]
+pub struct LongTermFuelTrimBank1 {
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<100, 128, { -100 }>, unit = "%")]
+    pub trim: f32,
+}
+
+/// PID 0x08, the same again for bank 2
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x08)]
+pub struct ShortTermFuelTrimBank2 {
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<100, 128, { -100 }>, unit = "%")]
+    pub trim: f32,
+}
+
+/// PID 0x09
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x09)]
+pub struct LongTermFuelTrimBank2 {
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<100, 128, { -100 }>, unit = "%")]
+    pub trim: f32,
+}
+
+/// PID 0x0B, `A`
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq, Eq)]
+#[can_extract(crate = "crate", pid = 0x0B)]
+pub struct IntakeManifoldPressure {
+    #[can_extract(offset = 0, unit = "kPa")]
+    pub pressure: u8,
+}
+
+/// PID 0x0C, `(256 * A + B) / 4`
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x0C)]
+pub struct EngineSpeed {
+    #[can_extract(offset = 0, extract = 2, use_big_endian, codec = Scaled<1, 4>, unit = "rpm")]
+    pub speed: f32,
+}
+
+/// PID 0x0D, `A`
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq, Eq)]
+#[can_extract(crate = "crate", pid = 0x0D)]
+pub struct VehicleSpeed {
+    #[can_extract(offset = 0, unit = "km/h")]
+    pub speed: u8,
+}
+
+/// PID 0x0E, `A / 2 - 64`, before top dead centre
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x0E)]
+pub struct TimingAdvance {
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<1, 2, { -64 }>, unit = "deg")]
+    pub advance: f32,
+}
+
+/// PID 0x0F, `A - 40`
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x0F)]
+pub struct IntakeAirTemperature {
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<1, 1, { -40 }>, unit = "degC")]
+    pub temperature: f32,
+}
+
+/// PID 0x10, `(256 * A + B) / 100`
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x10)]
+pub struct MafAirFlowRate {
+    #[can_extract(offset = 0, extract = 2, use_big_endian, codec = Scaled<1, 100>, unit = "g/s")]
+    pub rate: f32,
+}
+
+/// PID 0x11, `100 / 255 * A`
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x11)]
+pub struct ThrottlePosition {
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<100, 255>, unit = "%")]
+    pub position: f32,
+}
+
+/// PID 0x1F, `256 * A + B`
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq, Eq)]
+#[can_extract(crate = "crate", pid = 0x1F)]
+pub struct RunTime {
+    #[can_extract(offset = 0, use_big_endian, unit = "s")]
+    pub seconds: u16,
+}
+
+/// PID 0x2F, `100 / 255 * A`
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x2F)]
+pub struct FuelTankLevel {
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<100, 255>, unit = "%")]
+    pub level: f32,
+}
+
+/// PID 0x46, `A - 40`
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x46)]
+pub struct AmbientAirTemperature {
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<1, 1, { -40 }>, unit = "degC")]
+    pub temperature: f32,
+}
+
+/// PID 0x5C, `A - 40`
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(crate = "crate", pid = 0x5C)]
+pub struct OilTemperature {
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<1, 1, { -40 }>, unit = "degC")]
+    pub temperature: f32,
+}
@@ -0,0 +1,45 @@
+//! Serde support for derived messages
+//!
+//! With the serde feature, structs tagged `#[can_extract(serialize)]` serialise each member as a
+//! signal - the decoded value, plus the unit and raw value when the member asks for them - so the
+//! CAN context survives the trip into JSON and friends.
+
+use crate::Signal;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// A single serialised member, e.g. `{"value": 12.3, "unit": "km/h", "raw": 123}`
+#[derive(Debug)]
+pub struct SignalValue<'a, T> {
+    pub value: &'a T,
+    pub unit: Option<&'static str>,
+    pub raw: Option<u64>,
+}
+
+impl<T: Serialize> Serialize for SignalValue<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let len = 1 + self.unit.is_some() as usize + self.raw.is_some() as usize;
+        let mut state = serializer.serialize_struct("SignalValue", len)?;
+        state.serialize_field("value", self.value)?;
+        match self.unit {
+            Some(unit) => state.serialize_field("unit", unit)?,
+            None => state.skip_field("unit")?,
+        }
+        match self.raw {
+            Some(raw) => state.serialize_field("raw", &raw)?,
+            None => state.skip_field("raw")?,
+        }
+        state.end()
+    }
+}
+
+/// Valid signals serialise as their value, the rest as the variant name e.g. `"NotAvailable"`
+impl<T: Serialize> Serialize for Signal<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Signal::Valid(value) => value.serialize(serializer),
+            Signal::NotAvailable => serializer.serialize_unit_variant("Signal", 1, "NotAvailable"),
+            Signal::Error => serializer.serialize_unit_variant("Signal", 2, "Error"),
+            Signal::Reserved => serializer.serialize_unit_variant("Signal", 3, "Reserved"),
+        }
+    }
+}
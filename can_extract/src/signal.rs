@@ -0,0 +1,164 @@
+//! Signals that can report "not available" or "error" in place of a value
+//!
+//! J1939 reserves the top of every signal's raw range - all ones means the sender doesn't have
+//! the value, one below that means the value is faulty. Members typed `Signal<T>` or `Option<T>`
+//! have their raw value checked against those ranges before it's decoded, so a missing engine
+//! speed comes out as `Signal::NotAvailable` rather than 8191.875 rpm.
+//!
+//! `Option<T>` read through [`CanField`](crate::CanField), e.g. the elements of a `[Option<u8>; 4]`
+//! member, checks the same J1939 ranges - so a byte decodes to the same thing wherever it sits.
+
+use crate::codec::max_raw;
+use crate::Error;
+
+/// A decoded member, or the reason there isn't one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal<T> {
+    Valid(T),
+    /// The sender doesn't have the value
+    NotAvailable,
+    /// The sender has flagged the value as faulty
+    Error,
+    /// The raw value sits in a range the spec reserves
+    Reserved,
+}
+
+impl<T> Signal<T> {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Signal::Valid(_))
+    }
+
+    pub fn as_ref(&self) -> Signal<&T> {
+        match self {
+            Signal::Valid(value) => Signal::Valid(value),
+            Signal::NotAvailable => Signal::NotAvailable,
+            Signal::Error => Signal::Error,
+            Signal::Reserved => Signal::Reserved,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Signal<U> {
+        match self {
+            Signal::Valid(value) => Signal::Valid(f(value)),
+            Signal::NotAvailable => Signal::NotAvailable,
+            Signal::Error => Signal::Error,
+            Signal::Reserved => Signal::Reserved,
+        }
+    }
+}
+
+/// Anything but a valid value becomes None
+impl<T> From<Signal<T>> for Option<T> {
+    fn from(signal: Signal<T>) -> Self {
+        match signal {
+            Signal::Valid(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// None is sent as not available
+impl<T> From<Option<T>> for Signal<T> {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => Signal::Valid(value),
+            None => Signal::NotAvailable,
+        }
+    }
+}
+
+/// How the raw values that don't carry a value are recognised
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ranges {
+    /// The J1939-71 ranges for the signal's length
+    J1939,
+    /// Single raw values, from `not_available = ..`, `error = ..` and `reserved = ..` on the member
+    Values {
+        not_available: Option<u64>,
+        error: Option<u64>,
+        reserved: Option<u64>,
+    },
+}
+
+impl Ranges {
+    /// Works out what a raw value means, without decoding it
+    pub fn classify(&self, raw: u64, length: u16) -> Signal<u64> {
+        match *self {
+            Ranges::J1939 => classify_j1939(raw, length),
+            Ranges::Values { not_available, .. } if not_available == Some(raw) => {
+                Signal::NotAvailable
+            }
+            Ranges::Values { error, .. } if error == Some(raw) => Signal::Error,
+            Ranges::Values { reserved, .. } if reserved == Some(raw) => Signal::Reserved,
+            Ranges::Values { .. } => Signal::Valid(raw),
+        }
+    }
+
+    /// Classifies the raw value, and decodes it if it's valid
+    pub fn decode<T>(
+        &self,
+        raw: u64,
+        length: u16,
+        decode: impl FnOnce(u64) -> Result<T, Error>,
+    ) -> Result<Signal<T>, Error> {
+        match self.classify(raw, length) {
+            Signal::Valid(raw) => decode(raw).map(Signal::Valid),
+            Signal::NotAvailable => Ok(Signal::NotAvailable),
+            Signal::Error => Ok(Signal::Error),
+            Signal::Reserved => Ok(Signal::Reserved),
+        }
+    }
+
+    /// The raw value for a signal, failing when a valid value would read back as something else
+    pub fn encode<T>(
+        &self,
+        signal: Signal<T>,
+        length: u16,
+        encode: impl FnOnce(T) -> Result<u64, Error>,
+    ) -> Result<u64, Error> {
+        let raw = match (signal, *self) {
+            (Signal::Valid(value), _) => {
+                let raw = encode(value)?;
+                if !self.classify(raw, length).is_valid() {
+                    return Err(Error::ValueOutOfRange);
+                }
+                return Ok(raw);
+            }
+            (Signal::NotAvailable, Ranges::Values { not_available, .. }) => not_available,
+            (Signal::Error, Ranges::Values { error, .. }) => error,
+            (Signal::Reserved, Ranges::Values { reserved, .. }) => reserved,
+            (signal, Ranges::J1939) => raw_j1939(signal, length),
+        };
+        raw.ok_or(Error::ValueOutOfRange)
+    }
+}
+
+/// Signals under 8 bits lose their top two values, longer ones the top of their top byte
+fn classify_j1939(raw: u64, length: u16) -> Signal<u64> {
+    let max = max_raw(length);
+    match length {
+        0..=1 => Signal::Valid(raw),
+        2..=7 if raw == max => Signal::NotAvailable,
+        2..=7 if raw == max - 1 => Signal::Error,
+        2..=7 => Signal::Valid(raw),
+        _ => match (raw >> (length - 8)) & 0xFF {
+            0xFF => Signal::NotAvailable,
+            0xFE => Signal::Error,
+            0xFB..=0xFD => Signal::Reserved,
+            _ => Signal::Valid(raw),
+        },
+    }
+}
+
+/// The raw value sent in place of a value, None for valid signals and bools
+fn raw_j1939<T>(signal: Signal<T>, length: u16) -> Option<u64> {
+    let max = max_raw(length);
+    match (signal, length) {
+        (Signal::Valid(_), _) | (_, 0..=1) => None,
+        (Signal::NotAvailable, _) => Some(max),
+        (Signal::Error, 2..=7) => Some(max - 1),
+        (Signal::Reserved, 2..=7) => None,
+        (Signal::Error, _) => Some(0xFE << (length - 8)),
+        (Signal::Reserved, _) => Some(0xFB << (length - 8)),
+    }
+}
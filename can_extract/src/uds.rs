@@ -0,0 +1,530 @@
+//! ISO 14229 Unified Diagnostic Services
+//!
+//! UDS requests and responses travel as ISO-TP payloads - a service ID, then the service's
+//! parameters. Positive responses echo the service ID plus 0x40, negative ones are 0x7F
+//! followed by the service ID and a negative response code.
+//!
+//! Data identifier records are declared like frames, as a struct deriving CanDecode with a
+//! `did = ..` container attribute, and pulled out of a response with
+//! [`Response::decode_did`].
+
+use crate::{CanDecodeSlice, Error};
+use core::time::Duration;
+
+pub const DIAGNOSTIC_SESSION_CONTROL: u8 = 0x10;
+pub const ECU_RESET: u8 = 0x11;
+pub const CLEAR_DIAGNOSTIC_INFORMATION: u8 = 0x14;
+pub const READ_DTC_INFORMATION: u8 = 0x19;
+pub const READ_DATA_BY_IDENTIFIER: u8 = 0x22;
+pub const TESTER_PRESENT: u8 = 0x3E;
+pub const NEGATIVE_RESPONSE: u8 = 0x7F;
+
+/// Added to a service ID for its positive response
+const POSITIVE_RESPONSE_OFFSET: u8 = 0x40;
+/// Set on a sub-function to ask the server not to respond
+const SUPPRESS_RESPONSE: u8 = 0x80;
+
+/// Implemented by the derive for records declaring a `did`
+pub trait DataIdentifier: CanDecodeSlice {
+    const DID: u16;
+}
+
+/// Diagnostic sessions, as requested through DiagnosticSessionControl
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Session {
+    Default,
+    Programming,
+    Extended,
+    SafetySystem,
+    Other(u8),
+}
+
+impl From<u8> for Session {
+    fn from(session: u8) -> Self {
+        match session {
+            0x01 => Session::Default,
+            0x02 => Session::Programming,
+            0x03 => Session::Extended,
+            0x04 => Session::SafetySystem,
+            other => Session::Other(other),
+        }
+    }
+}
+
+impl From<Session> for u8 {
+    fn from(session: Session) -> Self {
+        match session {
+            Session::Default => 0x01,
+            Session::Programming => 0x02,
+            Session::Extended => 0x03,
+            Session::SafetySystem => 0x04,
+            Session::Other(other) => other,
+        }
+    }
+}
+
+/// Negative response codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nrc {
+    GeneralReject,
+    ServiceNotSupported,
+    SubFunctionNotSupported,
+    IncorrectMessageLength,
+    ResponseTooLong,
+    BusyRepeatRequest,
+    ConditionsNotCorrect,
+    RequestSequenceError,
+    RequestOutOfRange,
+    SecurityAccessDenied,
+    InvalidKey,
+    ExceededNumberOfAttempts,
+    RequiredTimeDelayNotExpired,
+    /// Not a failure - the server needs longer, and will respond later
+    ResponsePending,
+    SubFunctionNotSupportedInActiveSession,
+    ServiceNotSupportedInActiveSession,
+    Other(u8),
+}
+
+impl From<u8> for Nrc {
+    fn from(nrc: u8) -> Self {
+        match nrc {
+            0x10 => Nrc::GeneralReject,
+            0x11 => Nrc::ServiceNotSupported,
+            0x12 => Nrc::SubFunctionNotSupported,
+            0x13 => Nrc::IncorrectMessageLength,
+            0x14 => Nrc::ResponseTooLong,
+            0x21 => Nrc::BusyRepeatRequest,
+            0x22 => Nrc::ConditionsNotCorrect,
+            0x24 => Nrc::RequestSequenceError,
+            0x31 => Nrc::RequestOutOfRange,
+            0x33 => Nrc::SecurityAccessDenied,
+            0x35 => Nrc::InvalidKey,
+            0x36 => Nrc::ExceededNumberOfAttempts,
+            0x37 => Nrc::RequiredTimeDelayNotExpired,
+            0x78 => Nrc::ResponsePending,
+            0x7E => Nrc::SubFunctionNotSupportedInActiveSession,
+            0x7F => Nrc::ServiceNotSupportedInActiveSession,
+            other => Nrc::Other(other),
+        }
+    }
+}
+
+impl From<Nrc> for u8 {
+    fn from(nrc: Nrc) -> Self {
+        match nrc {
+            Nrc::GeneralReject => 0x10,
+            Nrc::ServiceNotSupported => 0x11,
+            Nrc::SubFunctionNotSupported => 0x12,
+            Nrc::IncorrectMessageLength => 0x13,
+            Nrc::ResponseTooLong => 0x14,
+            Nrc::BusyRepeatRequest => 0x21,
+            Nrc::ConditionsNotCorrect => 0x22,
+            Nrc::RequestSequenceError => 0x24,
+            Nrc::RequestOutOfRange => 0x31,
+            Nrc::SecurityAccessDenied => 0x33,
+            Nrc::InvalidKey => 0x35,
+            Nrc::ExceededNumberOfAttempts => 0x36,
+            Nrc::RequiredTimeDelayNotExpired => 0x37,
+            Nrc::ResponsePending => 0x78,
+            Nrc::SubFunctionNotSupportedInActiveSession => 0x7E,
+            Nrc::ServiceNotSupportedInActiveSession => 0x7F,
+            Nrc::Other(other) => other,
+        }
+    }
+}
+
+/// ReadDTCInformation reports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DtcReport {
+    /// 0x01, how many DTCs match the status mask
+    NumberByStatusMask(u8),
+    /// 0x02, the DTCs matching the status mask
+    ByStatusMask(u8),
+    /// 0x0A, every DTC the server supports
+    Supported,
+    Other {
+        sub_function: u8,
+        data: Vec<u8>,
+    },
+}
+
+/// A DTC along with its status bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dtc {
+    /// The 3 byte DTC number
+    pub code: u32,
+    pub status: u8,
+}
+
+/// ReadDTCInformation responses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DtcInformation {
+    Count {
+        availability_mask: u8,
+        format: u8,
+        count: u16,
+    },
+    /// Answers both ByStatusMask and Supported
+    Dtcs {
+        sub_function: u8,
+        availability_mask: u8,
+        dtcs: Vec<Dtc>,
+    },
+    Other {
+        sub_function: u8,
+        data: Vec<u8>,
+    },
+}
+
+/// A request from the tester
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    DiagnosticSessionControl {
+        session: Session,
+        suppress_response: bool,
+    },
+    EcuReset {
+        reset_type: u8,
+        suppress_response: bool,
+    },
+    ClearDiagnosticInformation {
+        /// The 3 byte DTC group, 0xFFFFFF for all of them
+        group: u32,
+    },
+    ReadDtcInformation(DtcReport),
+    ReadDataByIdentifier {
+        dids: Vec<u16>,
+    },
+    TesterPresent {
+        suppress_response: bool,
+    },
+    Other {
+        sid: u8,
+        data: Vec<u8>,
+    },
+}
+
+/// A response from the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    DiagnosticSessionControl {
+        session: Session,
+        /// How long the server may take to respond
+        p2: Duration,
+        /// How long the server may take after a ResponsePending
+        p2_extended: Duration,
+    },
+    EcuReset {
+        reset_type: u8,
+    },
+    ClearDiagnosticInformation,
+    ReadDtcInformation(DtcInformation),
+    /// The record length comes from the DID's layout, so with several DIDs requested the
+    /// records after the first one are left in `data`
+    ReadDataByIdentifier {
+        did: u16,
+        data: Vec<u8>,
+    },
+    TesterPresent,
+    Negative {
+        sid: u8,
+        nrc: Nrc,
+    },
+    Other {
+        sid: u8,
+        data: Vec<u8>,
+    },
+}
+
+/// Splits off the service ID, the first byte of every payload
+fn split_sid(payload: &[u8]) -> Result<(u8, &[u8]), Error> {
+    payload
+        .split_first()
+        .map(|(sid, data)| (*sid, data))
+        .ok_or(Error::InvalidSlicingLength)
+}
+
+fn byte(data: &[u8], index: usize) -> Result<u8, Error> {
+    data.get(index).copied().ok_or(Error::InvalidSlicingLength)
+}
+
+fn u16_be(data: &[u8], index: usize) -> Result<u16, Error> {
+    Ok(u16::from_be_bytes([
+        byte(data, index)?,
+        byte(data, index + 1)?,
+    ]))
+}
+
+fn u24_be(data: &[u8], index: usize) -> Result<u32, Error> {
+    Ok(u32::from_be_bytes([
+        0,
+        byte(data, index)?,
+        byte(data, index + 1)?,
+        byte(data, index + 2)?,
+    ]))
+}
+
+/// The low 3 bytes of a DTC, failing rather than dropping a top byte that's set
+fn u24_bytes(value: u32) -> Result<[u8; 3], Error> {
+    match value.to_be_bytes() {
+        [0, bytes @ ..] => Ok(bytes),
+        _ => Err(Error::ValueOutOfRange),
+    }
+}
+
+/// Splits `data` into records of `N` bytes, failing when there's a partial record left over
+fn records<const N: usize>(data: &[u8]) -> Result<&[[u8; N]], Error> {
+    match data.as_chunks::<N>() {
+        (records, []) => Ok(records),
+        _ => Err(Error::InvalidSlicingLength),
+    }
+}
+
+/// Timings are sent in whole units of `unit`, so fail on any that don't fit in 16 bits
+fn timing(duration: &Duration, unit: Duration) -> Result<[u8; 2], Error> {
+    u16::try_from(duration.as_nanos() / unit.as_nanos())
+        .map(u16::to_be_bytes)
+        .map_err(|_| Error::ValueOutOfRange)
+}
+
+fn sub_function(value: u8, suppress_response: bool) -> u8 {
+    match suppress_response {
+        true => value | SUPPRESS_RESPONSE,
+        false => value,
+    }
+}
+
+impl Request {
+    pub fn sid(&self) -> u8 {
+        match self {
+            Request::DiagnosticSessionControl { .. } => DIAGNOSTIC_SESSION_CONTROL,
+            Request::EcuReset { .. } => ECU_RESET,
+            Request::ClearDiagnosticInformation { .. } => CLEAR_DIAGNOSTIC_INFORMATION,
+            Request::ReadDtcInformation(_) => READ_DTC_INFORMATION,
+            Request::ReadDataByIdentifier { .. } => READ_DATA_BY_IDENTIFIER,
+            Request::TesterPresent { .. } => TESTER_PRESENT,
+            Request::Other { sid, .. } => *sid,
+        }
+    }
+
+    /// Lays the request out as an ISO-TP payload
+    pub fn to_payload(&self) -> Result<Vec<u8>, Error> {
+        let mut payload = vec![self.sid()];
+        match self {
+            Request::DiagnosticSessionControl {
+                session,
+                suppress_response,
+            } => payload.push(sub_function((*session).into(), *suppress_response)),
+            Request::EcuReset {
+                reset_type,
+                suppress_response,
+            } => payload.push(sub_function(*reset_type, *suppress_response)),
+            Request::ClearDiagnosticInformation { group } => {
+                payload.extend_from_slice(&u24_bytes(*group)?);
+            }
+            Request::ReadDtcInformation(report) => match report {
+                DtcReport::NumberByStatusMask(mask) => payload.extend_from_slice(&[0x01, *mask]),
+                DtcReport::ByStatusMask(mask) => payload.extend_from_slice(&[0x02, *mask]),
+                DtcReport::Supported => payload.push(0x0A),
+                DtcReport::Other { sub_function, data } => {
+                    payload.push(*sub_function);
+                    payload.extend_from_slice(data);
+                }
+            },
+            Request::ReadDataByIdentifier { dids } => {
+                for did in dids {
+                    payload.extend_from_slice(&did.to_be_bytes());
+                }
+            }
+            Request::TesterPresent { suppress_response } => {
+                payload.push(sub_function(0x00, *suppress_response))
+            }
+            Request::Other { data, .. } => payload.extend_from_slice(data),
+        }
+        Ok(payload)
+    }
+}
+
+impl CanDecodeSlice for Request {
+    fn from_slice(payload: &[u8]) -> Result<Self, Error> {
+        let (sid, data) = split_sid(payload)?;
+        Ok(match sid {
+            DIAGNOSTIC_SESSION_CONTROL => Request::DiagnosticSessionControl {
+                session: Session::from(byte(data, 0)? & !SUPPRESS_RESPONSE),
+                suppress_response: byte(data, 0)? & SUPPRESS_RESPONSE != 0,
+            },
+            ECU_RESET => Request::EcuReset {
+                reset_type: byte(data, 0)? & !SUPPRESS_RESPONSE,
+                suppress_response: byte(data, 0)? & SUPPRESS_RESPONSE != 0,
+            },
+            CLEAR_DIAGNOSTIC_INFORMATION => Request::ClearDiagnosticInformation {
+                group: u24_be(data, 0)?,
+            },
+            READ_DTC_INFORMATION => Request::ReadDtcInformation(match byte(data, 0)? {
+                0x01 => DtcReport::NumberByStatusMask(byte(data, 1)?),
+                0x02 => DtcReport::ByStatusMask(byte(data, 1)?),
+                0x0A => DtcReport::Supported,
+                sub_function => DtcReport::Other {
+                    sub_function,
+                    data: data[1..].to_vec(),
+                },
+            }),
+            READ_DATA_BY_IDENTIFIER => {
+                if data.is_empty() {
+                    return Err(Error::InvalidSlicingLength);
+                }
+                Request::ReadDataByIdentifier {
+                    dids: records::<2>(data)?
+                        .iter()
+                        .map(|did| u16::from_be_bytes(*did))
+                        .collect(),
+                }
+            }
+            TESTER_PRESENT => Request::TesterPresent {
+                suppress_response: byte(data, 0)? & SUPPRESS_RESPONSE != 0,
+            },
+            sid => Request::Other {
+                sid,
+                data: data.to_vec(),
+            },
+        })
+    }
+}
+
+impl Response {
+    /// The service this responds to
+    pub fn sid(&self) -> u8 {
+        match self {
+            Response::DiagnosticSessionControl { .. } => DIAGNOSTIC_SESSION_CONTROL,
+            Response::EcuReset { .. } => ECU_RESET,
+            Response::ClearDiagnosticInformation => CLEAR_DIAGNOSTIC_INFORMATION,
+            Response::ReadDtcInformation(_) => READ_DTC_INFORMATION,
+            Response::ReadDataByIdentifier { .. } => READ_DATA_BY_IDENTIFIER,
+            Response::TesterPresent => TESTER_PRESENT,
+            Response::Negative { sid, .. } | Response::Other { sid, .. } => *sid,
+        }
+    }
+
+    /// Lays the response out as an ISO-TP payload, failing on values the layout can't hold
+    pub fn to_payload(&self) -> Result<Vec<u8>, Error> {
+        if let Response::Negative { sid, nrc } = self {
+            return Ok(vec![NEGATIVE_RESPONSE, *sid, (*nrc).into()]);
+        }
+        let mut payload = vec![self.sid().wrapping_add(POSITIVE_RESPONSE_OFFSET)];
+        match self {
+            Response::DiagnosticSessionControl {
+                session,
+                p2,
+                p2_extended,
+            } => {
+                payload.push((*session).into());
+                payload.extend_from_slice(&timing(p2, Duration::from_millis(1))?);
+                payload.extend_from_slice(&timing(p2_extended, Duration::from_millis(10))?);
+            }
+            Response::EcuReset { reset_type } => payload.push(*reset_type),
+            Response::ReadDtcInformation(information) => match information {
+                DtcInformation::Count {
+                    availability_mask,
+                    format,
+                    count,
+                } => {
+                    payload.extend_from_slice(&[0x01, *availability_mask, *format]);
+                    payload.extend_from_slice(&count.to_be_bytes());
+                }
+                DtcInformation::Dtcs {
+                    sub_function,
+                    availability_mask,
+                    dtcs,
+                } => {
+                    payload.extend_from_slice(&[*sub_function, *availability_mask]);
+                    for dtc in dtcs {
+                        payload.extend_from_slice(&u24_bytes(dtc.code)?);
+                        payload.push(dtc.status);
+                    }
+                }
+                DtcInformation::Other { sub_function, data } => {
+                    payload.push(*sub_function);
+                    payload.extend_from_slice(data);
+                }
+            },
+            Response::ReadDataByIdentifier { did, data } => {
+                payload.extend_from_slice(&did.to_be_bytes());
+                payload.extend_from_slice(data);
+            }
+            Response::TesterPresent => payload.push(0x00),
+            Response::Other { data, .. } => payload.extend_from_slice(data),
+            Response::ClearDiagnosticInformation | Response::Negative { .. } => {}
+        }
+        Ok(payload)
+    }
+
+    /// Decodes a ReadDataByIdentifier record through its derived layout
+    ///
+    /// Gives None when this isn't a positive response for `T`'s DID.
+    pub fn decode_did<T: DataIdentifier>(&self) -> Result<Option<T>, Error> {
+        let Response::ReadDataByIdentifier { did, data } = self else {
+            return Ok(None);
+        };
+        if *did != T::DID {
+            return Ok(None);
+        }
+        T::from_slice(data).map(Some)
+    }
+}
+
+impl CanDecodeSlice for Response {
+    fn from_slice(payload: &[u8]) -> Result<Self, Error> {
+        let (sid, data) = split_sid(payload)?;
+        if sid == NEGATIVE_RESPONSE {
+            return Ok(Response::Negative {
+                sid: byte(data, 0)?,
+                nrc: Nrc::from(byte(data, 1)?),
+            });
+        }
+
+        let sid = sid.wrapping_sub(POSITIVE_RESPONSE_OFFSET);
+        Ok(match sid {
+            DIAGNOSTIC_SESSION_CONTROL => Response::DiagnosticSessionControl {
+                session: Session::from(byte(data, 0)?),
+                p2: Duration::from_millis(u16_be(data, 1)? as u64),
+                p2_extended: Duration::from_millis(u16_be(data, 3)? as u64 * 10),
+            },
+            ECU_RESET => Response::EcuReset {
+                reset_type: byte(data, 0)?,
+            },
+            CLEAR_DIAGNOSTIC_INFORMATION => Response::ClearDiagnosticInformation,
+            READ_DTC_INFORMATION => Response::ReadDtcInformation(match byte(data, 0)? {
+                0x01 => DtcInformation::Count {
+                    availability_mask: byte(data, 1)?,
+                    format: byte(data, 2)?,
+                    count: u16_be(data, 3)?,
+                },
+                sub_function @ (0x02 | 0x0A) => DtcInformation::Dtcs {
+                    sub_function,
+                    availability_mask: byte(data, 1)?,
+                    dtcs: records::<4>(&data[2..])?
+                        .iter()
+                        .map(|&[high, middle, low, status]| Dtc {
+                            code: u32::from_be_bytes([0, high, middle, low]),
+                            status,
+                        })
+                        .collect(),
+                },
+                sub_function => DtcInformation::Other {
+                    sub_function,
+                    data: data[1..].to_vec(),
+                },
+            }),
+            READ_DATA_BY_IDENTIFIER => Response::ReadDataByIdentifier {
+                did: u16_be(data, 0)?,
+                data: data[2..].to_vec(),
+            },
+            TESTER_PRESENT => Response::TesterPresent,
+            _ => Response::Other {
+                sid,
+                data: data.to_vec(),
+            },
+        })
+    }
+}
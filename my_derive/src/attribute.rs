@@ -3,13 +3,84 @@ use virtue::utils::{parse_tagged_attribute, ParsedAttribute};
 
 use std::str::FromStr;
 
+/// Which end of a big endian signal its `start_bit` points at
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BitNumbering {
+    /// The most significant bit, as DBC files count it
+    #[default]
+    Msb,
+    /// The least significant bit, as ARXML and KCD files count it
+    Lsb,
+}
+
+impl BitNumbering {
+    fn parse(val: &impl AttributeValue) -> Result<Self> {
+        match parse_str(val)?.as_str() {
+            "msb" => Ok(Self::Msb),
+            "lsb" => Ok(Self::Lsb),
+            _ => Err(Error::custom_at(
+                "Should be either \"msb\" or \"lsb\"",
+                val.span(),
+            )),
+        }
+    }
+}
+
+/// What the decoder does with a physical value outside a member's `min` and `max`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum RangePolicy {
+    /// Fail the decode
+    #[default]
+    Error,
+    /// Replace the value with the bound it crossed
+    Clamp,
+    /// Keep the value, setting its bit in CanValidate::out_of_range
+    Flag,
+}
+
+impl RangePolicy {
+    fn parse(val: &impl AttributeValue) -> Result<Self> {
+        match parse_str(val)?.as_str() {
+            "error" => Ok(Self::Error),
+            "clamp" => Ok(Self::Clamp),
+            "flag" => Ok(Self::Flag),
+            _ => Err(Error::custom_at(
+                "Should be one of \"error\", \"clamp\" or \"flag\"",
+                val.span(),
+            )),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ContainerAttributes {
     pub crate_name: String,
     pub bounds: Option<(String, Literal)>,
-    pub decode_bounds: Option<(String, Literal)>,
-    pub borrow_decode_bounds: Option<(String, Literal)>,
     pub encode_bounds: Option<(String, Literal)>,
+    pub decode_bounds: Option<(String, Literal)>,
+    pub view: bool,
+    /// Derive proptest's Arbitrary too, when the proptest feature is on
+    pub arbitrary: bool,
+    /// Derive serde's Serialize too, when the serde feature is on
+    pub serialize: bool,
+    pub use_big_endian: bool,
+    pub bit_numbering: BitNumbering,
+    pub out_of_range: RangePolicy,
+    pub id: Option<u32>,
+    pub name: Option<String>,
+    pub dlc: Option<u8>,
+    pub pgn: Option<u32>,
+    pub fast_packet: bool,
+    pub did: Option<u16>,
+    pub pid: Option<u8>,
+    pub pdo: Option<u8>,
+    pub node: Option<u8>,
+    pub rpdo: bool,
+    pub e2e_profile: Option<u8>,
+    pub e2e_data_id: Option<u16>,
+    pub e2e_data_id_mode: Option<String>,
+    pub e2e_data_id_list: Option<Vec<u8>>,
+    pub e2e_max_delta_counter: Option<u8>,
 }
 
 impl Default for ContainerAttributes {
@@ -17,9 +88,61 @@ impl Default for ContainerAttributes {
         Self {
             crate_name: "::can_extract".to_string(),
             bounds: None,
-            decode_bounds: None,
             encode_bounds: None,
-            borrow_decode_bounds: None,
+            decode_bounds: None,
+            view: false,
+            arbitrary: false,
+            serialize: false,
+            use_big_endian: false,
+            bit_numbering: BitNumbering::Msb,
+            out_of_range: RangePolicy::Error,
+            id: None,
+            name: None,
+            dlc: None,
+            pgn: None,
+            fast_packet: false,
+            did: None,
+            pid: None,
+            pdo: None,
+            node: None,
+            rpdo: false,
+            e2e_profile: None,
+            e2e_data_id: None,
+            e2e_data_id_mode: None,
+            e2e_data_id_list: None,
+            e2e_max_delta_counter: None,
+        }
+    }
+}
+
+impl ContainerAttributes {
+    /// Bytes the members can span - a reassembled fast packet, else a single CAN frame
+    /// UDS records are as long as their response, so are only checked against it when decoded
+    pub fn max_len(&self) -> Option<u16> {
+        match (self.fast_packet, self.did) {
+            (true, _) => Some(223),
+            (false, Some(_)) => None,
+            (false, None) => Some(8),
+        }
+    }
+
+    /// Whether the members always fit a single CAN frame, so can be read from and written to
+    /// `[u8; 8]` as well as slices
+    pub fn single_frame(&self) -> bool {
+        self.max_len().is_some_and(|max_len| max_len <= 8)
+    }
+
+    /// The declared ID, or the COB-ID of a PDO from the predefined connection set
+    pub fn can_id(&self) -> Option<u32> {
+        match (self.pdo, self.node) {
+            (Some(pdo), Some(node)) => {
+                let function = match self.rpdo {
+                    true => pdo as u32 * 2 + 2,
+                    false => pdo as u32 * 2 + 1,
+                };
+                Some((function << 7) | node as u32)
+            }
+            _ => self.id,
         }
     }
 }
@@ -50,34 +173,155 @@ impl FromAttribute for ContainerAttributes {
                         return Err(Error::custom_at("Should be a literal str", val.span()));
                     }
                 }
-                ParsedAttribute::Property(key, val) if key.to_string() == "decode_bounds" => {
+                ParsedAttribute::Property(key, val) if key.to_string() == "encode_bounds" => {
                     let val_string = val.to_string();
                     if val_string.starts_with('"') && val_string.ends_with('"') {
-                        result.decode_bounds =
+                        result.encode_bounds =
                             Some((val_string[1..val_string.len() - 1].to_string(), val));
                     } else {
                         return Err(Error::custom_at("Should be a literal str", val.span()));
                     }
                 }
-                ParsedAttribute::Property(key, val) if key.to_string() == "encode_bounds" => {
+                ParsedAttribute::Property(key, val) if key.to_string() == "decode_bounds" => {
                     let val_string = val.to_string();
                     if val_string.starts_with('"') && val_string.ends_with('"') {
-                        result.encode_bounds =
+                        result.decode_bounds =
                             Some((val_string[1..val_string.len() - 1].to_string(), val));
                     } else {
                         return Err(Error::custom_at("Should be a literal str", val.span()));
                     }
                 }
+                ParsedAttribute::Property(key, val) if key.to_string() == "byte_order" => {
+                    result.use_big_endian = match parse_str(&val)?.as_str() {
+                        "big" => true,
+                        "little" => false,
+                        _ => {
+                            return Err(Error::custom_at(
+                                "Should be either \"big\" or \"little\"",
+                                val.span(),
+                            ))
+                        }
+                    };
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "bit_numbering" => {
+                    result.bit_numbering = BitNumbering::parse(&val)?;
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "out_of_range" => {
+                    result.out_of_range = RangePolicy::parse(&val)?;
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "id" => {
+                    let id = parse_int(&val)?;
+                    if id > 0x1FFF_FFFF {
+                        return Err(Error::custom_at("CAN IDs are at most 29 bits", val.span()));
+                    }
+                    result.id = Some(id);
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "name" => {
+                    result.name = Some(parse_str(&val)?);
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "dlc" => {
+                    result.dlc = Some(parse_int(&val)?);
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "pgn" => {
+                    let pgn: u32 = parse_int(&val)?;
+                    if pgn > 0x3_FFFF {
+                        return Err(Error::custom_at("PGNs are at most 18 bits", val.span()));
+                    }
+                    if (pgn >> 8) & 0xFF < 240 && pgn & 0xFF != 0 {
+                        return Err(Error::custom_at(
+                            "A PDU1 PGN has the destination in its low byte, so it must be 0",
+                            val.span(),
+                        ));
+                    }
+                    result.pgn = Some(pgn);
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "did" => {
+                    result.did = Some(parse_int(&val)?);
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "pid" => {
+                    result.pid = Some(parse_int(&val)?);
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "pdo" => {
+                    let pdo = parse_int(&val)?;
+                    if !(1..=4).contains(&pdo) {
+                        return Err(Error::custom_at(
+                            "Only PDOs 1 to 4 have a predefined COB-ID",
+                            val.span(),
+                        ));
+                    }
+                    result.pdo = Some(pdo);
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "node" => {
+                    let node = parse_int(&val)?;
+                    if !(1..=127).contains(&node) {
+                        return Err(Error::custom_at(
+                            "CANopen node IDs are 1 to 127",
+                            val.span(),
+                        ));
+                    }
+                    result.node = Some(node);
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "e2e_profile" => {
+                    let profile = parse_int(&val)?;
+                    if profile != 1 && profile != 2 {
+                        return Err(Error::custom_at(
+                            "Only E2E profiles 1 and 2 are supported",
+                            val.span(),
+                        ));
+                    }
+                    result.e2e_profile = Some(profile);
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "e2e_data_id" => {
+                    result.e2e_data_id = Some(parse_int(&val)?);
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "e2e_data_id_mode" => {
+                    let mode = match parse_str(&val)?.as_str() {
+                        "both" => "Both",
+                        "alt" => "Alt",
+                        "low" => "Low",
+                        _ => {
+                            return Err(Error::custom_at(
+                                "Should be one of \"both\", \"alt\" or \"low\"",
+                                val.span(),
+                            ))
+                        }
+                    };
+                    result.e2e_data_id_mode = Some(mode.to_string());
+                }
+                ParsedAttribute::Property(key, val) if key.to_string() == "e2e_data_id_list" => {
+                    let list = parse_str(&val)?
+                        .split(',')
+                        .map(|id| parse_int_str(id).and_then(|id| u8::try_from(id).ok()))
+                        .collect::<Option<Vec<u8>>>();
+                    match list {
+                        Some(list) if list.len() == 16 => result.e2e_data_id_list = Some(list),
+                        _ => {
+                            return Err(Error::custom_at(
+                                "Should be a comma separated list of 16 byte values",
+                                val.span(),
+                            ))
+                        }
+                    }
+                }
                 ParsedAttribute::Property(key, val)
-                    if key.to_string() == "borrow_decode_bounds" =>
+                    if key.to_string() == "e2e_max_delta_counter" =>
                 {
-                    let val_string = val.to_string();
-                    if val_string.starts_with('"') && val_string.ends_with('"') {
-                        result.borrow_decode_bounds =
-                            Some((val_string[1..val_string.len() - 1].to_string(), val));
-                    } else {
-                        return Err(Error::custom_at("Should be a literal str", val.span()));
-                    }
+                    result.e2e_max_delta_counter = Some(parse_int(&val)?);
+                }
+                ParsedAttribute::Tag(i) if i.to_string() == "view" => {
+                    result.view = true;
+                }
+                ParsedAttribute::Tag(i) if i.to_string() == "arbitrary" => {
+                    result.arbitrary = true;
+                }
+                ParsedAttribute::Tag(i) if i.to_string() == "serialize" => {
+                    result.serialize = true;
+                }
+                ParsedAttribute::Tag(i) if i.to_string() == "rpdo" => {
+                    result.rpdo = true;
+                }
+                ParsedAttribute::Tag(i) if i.to_string() == "fast_packet" => {
+                    result.fast_packet = true;
                 }
                 ParsedAttribute::Tag(i) => {
                     return Err(Error::custom_at("Unknown field attribute", i.span()))
@@ -88,53 +332,169 @@ impl FromAttribute for ContainerAttributes {
                 _ => {}
             }
         }
+        if let (Some(dlc), Some(max_len)) = (result.dlc, result.max_len()) {
+            if dlc as u16 > max_len {
+                return Err(Error::custom(format!(
+                    "Invalid dlc, must be at most {}",
+                    max_len
+                )));
+            }
+        }
+        if result.id.is_some() && result.pgn.is_some() {
+            return Err(Error::custom(
+                "Use either id or pgn, a J1939 ID depends on the sender",
+            ));
+        }
+        if result.fast_packet && result.pgn.is_none() {
+            return Err(Error::custom(
+                "fast_packet marks an NMEA 2000 PGN, so needs pgn",
+            ));
+        }
+        if result.pdo.is_some() != result.node.is_some() {
+            return Err(Error::custom("A PDO needs both pdo and node"));
+        }
+        if result.rpdo && result.pdo.is_none() {
+            return Err(Error::custom("rpdo marks a PDO, so needs pdo and node"));
+        }
+        if result.pdo.is_some() && (result.id.is_some() || result.pgn.is_some()) {
+            return Err(Error::custom(
+                "Use either id, pgn or pdo, a PDO's COB-ID comes from its node",
+            ));
+        }
+        match result.e2e_profile {
+            Some(1) if result.e2e_data_id.is_none() => {
+                return Err(Error::custom("E2E profile 1 needs an e2e_data_id"))
+            }
+            Some(2) if result.e2e_data_id_list.is_none() => {
+                return Err(Error::custom("E2E profile 2 needs an e2e_data_id_list"))
+            }
+            _ => {}
+        }
         Ok(Some(result))
     }
 }
 
 #[derive(Default, Debug)]
 pub struct FieldAttributes {
+    pub codec: Option<String>,
     pub offset: Option<u8>,
+    pub start_bit: Option<u16>,
+    pub length: Option<u16>,
     pub extract_bytes: Option<u8>,
     pub use_big_endian: bool,
+    pub use_little_endian: bool,
+    pub bit_numbering: Option<BitNumbering>,
     pub use_decoder: Option<String>,
+    pub use_encoder: Option<String>,
+    pub unit: Option<String>,
+    pub serialize_raw: bool,
+    pub ieee_float: bool,
+    pub spn: Option<u32>,
+    pub not_available: Option<u64>,
+    pub error: Option<u64>,
+    pub reserved: Option<u64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub out_of_range: Option<RangePolicy>,
+    /// `factor = ..` as an exact ratio, numerator then denominator
+    pub factor: Option<(i64, i64)>,
 }
 
 impl FromAttribute for FieldAttributes {
     fn parse(group: &Group) -> Result<Option<Self>> {
-        let attributes = match parse_tagged_attribute(group, "can_extract")? {
+        let attributes = match parse_field_attribute(group)? {
             Some(body) => body,
             None => return Ok(None),
         };
         let mut result = Self::default();
         for attribute in attributes {
             match attribute {
-                ParsedAttribute::Tag(key) => match key.to_string().as_str() {
+                FieldItem::Tag(key) => match key.to_string().as_str() {
                     "use_big_endian" => {
                         result.use_big_endian = true;
                     }
+                    "use_little_endian" => {
+                        result.use_little_endian = true;
+                    }
+                    "serialize_raw" => {
+                        result.serialize_raw = true;
+                    }
+                    "ieee_float" => {
+                        result.ieee_float = true;
+                    }
                     _ => {
                         return Err(Error::custom_at("Unknown field attribute", key.span()));
                     }
                 },
-                ParsedAttribute::Property(key, value) => {
+                FieldItem::Property(key, value) => {
                     let str = value.to_string();
                     match key.to_string().as_str() {
                         "offset" => {
                             let Ok(offset_val) = u8::from_str(&str) else {
                                 return Err(Error::custom_at("Invalid offset value", key.span()))
                             };
-                            if offset_val >= 8 {
+                            result.offset = Some(offset_val);
+                        }
+                        "start_bit" => {
+                            // Checked against how long the struct can be in field_infos
+                            result.start_bit = Some(parse_int(&value)?);
+                        }
+                        "length" => {
+                            let length = parse_int(&value)?;
+                            if length == 0 || length > 64 {
                                 return Err(Error::custom_at(
-                                    "Invalid offset, must be less than 8",
+                                    "Invalid length, must be between 1 and 64 bits",
                                     key.span(),
                                 ));
                             }
-                            result.offset = Some(offset_val);
+                            result.length = Some(length);
+                        }
+                        "bit_numbering" => {
+                            result.bit_numbering = Some(BitNumbering::parse(&value)?);
+                        }
+                        "codec" => {
+                            result.codec = Some(str);
                         }
                         "use_decoder" => {
                             result.use_decoder = Some(str.replace("\"", ""));
                         }
+                        "use_encoder" => {
+                            result.use_encoder = Some(str.replace("\"", ""));
+                        }
+                        "unit" => {
+                            result.unit = Some(parse_str(&value)?);
+                        }
+                        "spn" => {
+                            let spn = parse_int(&value)?;
+                            if spn > 0x7_FFFF {
+                                return Err(Error::custom_at(
+                                    "SPNs are at most 19 bits",
+                                    key.span(),
+                                ));
+                            }
+                            result.spn = Some(spn);
+                        }
+                        "not_available" => {
+                            result.not_available = Some(parse_int(&value)?);
+                        }
+                        "error" => {
+                            result.error = Some(parse_int(&value)?);
+                        }
+                        "reserved" => {
+                            result.reserved = Some(parse_int(&value)?);
+                        }
+                        "min" => {
+                            result.min = Some(parse_float(&value)?);
+                        }
+                        "max" => {
+                            result.max = Some(parse_float(&value)?);
+                        }
+                        "out_of_range" => {
+                            result.out_of_range = Some(RangePolicy::parse(&value)?);
+                        }
+                        "factor" => {
+                            result.factor = Some(parse_ratio(&value)?);
+                        }
                         "extract" => {
                             result.extract_bytes = u8::from_str(&str)
                                 .map_err(|e| {
@@ -150,9 +510,166 @@ impl FromAttribute for FieldAttributes {
                         }
                     }
                 }
-                _ => {}
             }
         }
+        if let (Some(min), Some(max)) = (result.min, result.max) {
+            if min > max {
+                return Err(Error::custom("min can't be more than max"));
+            }
+        }
+        if result.use_big_endian && result.use_little_endian {
+            return Err(Error::custom(
+                "Use either use_big_endian or use_little_endian, not both",
+            ));
+        }
         Ok(Some(result))
     }
 }
+
+/// An item in a member's `#[can_extract(..)]`
+/// virtue's parse_tagged_attribute only takes a single literal after `=`, but member values
+/// can also be type paths like `codec = Scaled<1, 1000>`
+enum FieldItem {
+    Tag(Ident),
+    Property(Ident, Value),
+}
+
+/// The tokens after `key =`, up to the next comma outside of any `<..>`
+struct Value(Vec<TokenTree>);
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        TokenStream::from_iter(self.0.iter().cloned()).fmt(f)
+    }
+}
+
+fn parse_field_attribute(group: &Group) -> Result<Option<Vec<FieldItem>>> {
+    let mut stream = group.stream().into_iter();
+    match stream.next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "can_extract" => {}
+        _ => return Ok(None),
+    }
+    let Some(TokenTree::Group(group)) = stream.next() else {
+        return Ok(None);
+    };
+
+    let mut result = Vec::new();
+    let mut tokens = group.stream().into_iter();
+    while let Some(token) = tokens.next() {
+        let TokenTree::Ident(key) = token else {
+            return Err(Error::custom_at(
+                "Expected `key` or `key = value`",
+                token.span(),
+            ));
+        };
+        match tokens.next() {
+            None => result.push(FieldItem::Tag(key)),
+            Some(TokenTree::Punct(p)) if p.as_char() == ',' => result.push(FieldItem::Tag(key)),
+            Some(TokenTree::Punct(p)) if p.as_char() == '=' => {
+                let mut value = Vec::new();
+                let mut depth = 0usize;
+                for token in tokens.by_ref() {
+                    match &token {
+                        TokenTree::Punct(p) if p.as_char() == ',' && depth == 0 => break,
+                        TokenTree::Punct(p) if p.as_char() == '<' => depth += 1,
+                        TokenTree::Punct(p) if p.as_char() == '>' => {
+                            depth = depth.saturating_sub(1)
+                        }
+                        _ => {}
+                    }
+                    value.push(token);
+                }
+                if value.is_empty() {
+                    return Err(Error::custom_at("Expected a value after `=`", p.span()));
+                }
+                result.push(FieldItem::Property(key, Value(value)));
+            }
+            Some(token) => return Err(Error::custom_at("Expected `,` or `=`", token.span())),
+        }
+    }
+    Ok(Some(result))
+}
+
+/// Attribute values are either virtue literals, or our own member values
+trait AttributeValue: std::fmt::Display {
+    fn span(&self) -> Span;
+}
+
+impl AttributeValue for Literal {
+    fn span(&self) -> Span {
+        Literal::span(self)
+    }
+}
+
+impl AttributeValue for Value {
+    fn span(&self) -> Span {
+        self.0
+            .first()
+            .map(|token| token.span())
+            .unwrap_or_else(Span::call_site)
+    }
+}
+
+/// Strips the quotes off a literal str
+fn parse_str(val: &impl AttributeValue) -> Result<String> {
+    let val_string = val.to_string();
+    if val_string.starts_with('"') && val_string.ends_with('"') {
+        Ok(val_string[1..val_string.len() - 1].to_string())
+    } else {
+        Err(Error::custom_at("Should be a literal str", val.span()))
+    }
+}
+
+/// Parses an integer literal, which may be hex, octal or binary and have a type suffix
+fn parse_int<T: TryFrom<u64>>(val: &impl AttributeValue) -> Result<T> {
+    parse_int_str(&val.to_string())
+        .and_then(|v| T::try_from(v).ok())
+        .ok_or_else(|| Error::custom_at("Should be an integer literal in range", val.span()))
+}
+
+/// Parses a number for a physical value, which may be negative or have a fraction
+fn parse_float(val: &impl AttributeValue) -> Result<f64> {
+    let val_string = val.to_string().replace([' ', '_'], "");
+    f64::from_str(val_string.trim_end_matches("f64").trim_end_matches("f32"))
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| Error::custom_at("Should be a number", val.span()))
+}
+
+/// Parses a decimal like `0.01` as the exact ratio 1/100, so it can be a Scaled codec
+fn parse_ratio(val: &impl AttributeValue) -> Result<(i64, i64)> {
+    let err = || Error::custom_at("Should be a non-zero decimal number", val.span());
+    let val_string = val.to_string().replace([' ', '_'], "");
+    let (negative, digits) = match val_string.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, val_string.as_str()),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if !(whole.chars().chain(fraction.chars())).all(|c| c.is_ascii_digit()) {
+        return Err(err());
+    }
+    let mut num = i64::from_str(&format!("{}{}", whole, fraction)).map_err(|_| err())?;
+    let mut den = 10i64.checked_pow(fraction.len() as u32).ok_or_else(err)?;
+    if num == 0 {
+        return Err(err());
+    }
+    let (mut a, mut b) = (num, den);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    num /= a;
+    den /= a;
+    Ok((if negative { -num } else { num }, den))
+}
+
+fn parse_int_str(s: &str) -> Option<u64> {
+    let s = s.trim().replace('_', "");
+    let (digits, radix) = match s.get(..2) {
+        Some("0x") | Some("0X") => (&s[2..], 16),
+        Some("0o") => (&s[2..], 8),
+        Some("0b") => (&s[2..], 2),
+        _ => (&s[..], 10),
+    };
+    let digits = digits.split(['u', 'i']).next()?;
+    u64::from_str_radix(digits, radix).ok()
+}
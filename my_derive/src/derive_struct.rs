@@ -1,121 +1,344 @@
 #![allow(unused)]
 
-use crate::attribute::{ContainerAttributes, FieldAttributes};
-use virtue::generate::Generator;
-use virtue::parse::{Fields, IdentOrIndex};
+use crate::attribute::{BitNumbering, ContainerAttributes, FieldAttributes, RangePolicy};
+use virtue::generate::{Generator, ImplFor, Parent, StreamBuilder};
+use virtue::parse::{Fields, Visibility};
 use virtue::prelude::*;
 
 pub(crate) struct DeriveStruct {
     pub fields: Option<Fields>,
     pub attributes: ContainerAttributes,
+    pub visibility: Visibility,
+    /// Type parameters, whose members are bound on the traits they need rather than checked
+    pub generic_types: Vec<String>,
+}
+
+/// A struct member, named or not, along with its parsed can_extract attributes
+struct FieldInfo {
+    /// Member name, or its index in a tuple struct
+    member: String,
+    span: Span,
+    type_str: String,
+    /// The type actually decoded - `T` for `Signal<T>` and `Option<T>` members
+    value_type: String,
+    wrapper: Option<Wrapper>,
+    attributes: FieldAttributes,
+    /// The member's `out_of_range`, else the struct's
+    range_policy: RangePolicy,
+    /// Set when the member is a uom quantity
+    uom: Option<UomQuantity>,
+}
+
+/// A uom quantity member, whose codec works on the storage type in `unit`
+struct UomQuantity {
+    /// `f32` or `f64`
+    storage: String,
+    /// Path to the unit, e.g. `uom::si::velocity::kilometer_per_hour`
+    unit: String,
+}
+
+/// Member types that can stand in for a value that isn't available
+#[derive(Clone, Copy, PartialEq)]
+enum Wrapper {
+    Signal,
+    Option,
+}
+
+/// The two decode traits, for whole frames and for payloads of any length
+#[derive(Clone, Copy, PartialEq)]
+enum DecodeTrait {
+    Frame,
+    Slice,
 }
 
 impl DeriveStruct {
-    pub fn generate_encode(self, generator: &mut Generator) -> Result<()> {
+    fn is_tuple(&self) -> bool {
+        matches!(self.fields, Some(Fields::Tuple(_)))
+    }
+
+    /// Starts an impl of `trait_name`, bounding each generic member on what it takes to read and
+    /// write it, unless `bounds` overrides them. `generic_bound` goes on every type parameter
+    /// either way
+    fn impl_for<'a>(
+        &self,
+        generator: &'a mut Generator,
+        trait_name: impl Into<String>,
+        bounds: Option<&(String, Literal)>,
+        generic_bound: Option<&str>,
+    ) -> Result<ImplFor<'a, Generator>> {
         let crate_name = &self.attributes.crate_name;
-        generator
-            .impl_for(&format!("{}::CanDecode", crate_name))
-            .modify_generic_constraints(|generics, where_constraints| {
-                if let Some((bounds, lit)) =
-                    (self.attributes.encode_bounds.as_ref()).or(self.attributes.bounds.as_ref())
+        let fields = self.field_infos()?;
+        let mut impl_for = generator.impl_for(trait_name);
+        impl_for.modify_generic_constraints(|generics, where_constraints| {
+            if let Some((bounds, lit)) = bounds {
+                where_constraints.clear();
+                where_constraints
+                    .push_parsed_constraint(bounds)
+                    .map_err(|e| e.with_span(lit.span()))?;
+            }
+            for generic in generics.iter_generics() {
+                let name = generic.ident.to_string();
+                if bounds.is_none() {
+                    for field in fields.iter().filter(|field| field.value_type == name) {
+                        if let Some(bound) = field.generic_bound(crate_name) {
+                            where_constraints.push_parsed_constraint(bound)?;
+                        }
+                    }
+                }
+                if let Some(generic_bound) = generic_bound {
+                    where_constraints.push_constraint(generic, generic_bound)?;
+                }
+            }
+            Ok(())
+        })?;
+        Ok(impl_for)
+    }
+
+    /// Collects the struct members, checking they all have an offset
+    fn field_infos(&self) -> Result<Vec<FieldInfo>> {
+        let fields = match self.fields.as_ref() {
+            None => Vec::new(),
+            Some(Fields::Struct(fields)) => fields
+                .iter()
+                .map(|(ident, field)| (ident.to_string(), ident.span(), field))
+                .collect(),
+            Some(Fields::Tuple(fields)) => fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| (index.to_string(), field.span(), field))
+                .collect::<Vec<_>>(),
+        };
+
+        let mut infos = Vec::new();
+        for (member, span, field) in fields {
+            let mut attributes = field
+                .attributes
+                .get_attribute::<FieldAttributes>()?
+                .unwrap_or_default();
+            let type_str = field.type_string();
+            let (wrapper, value_type) = split_wrapper(&type_str);
+            if attributes.ieee_float {
+                apply_ieee_float(
+                    span,
+                    &value_type,
+                    &self.attributes.crate_name,
+                    &mut attributes,
+                )?;
+            }
+
+            // The struct's byte order and bit numbering, unless the member says otherwise
+            if self.attributes.use_big_endian && !attributes.use_little_endian {
+                attributes.use_big_endian = true;
+            }
+            let bit_numbering = attributes
+                .bit_numbering
+                .unwrap_or(self.attributes.bit_numbering);
+            if let (BitNumbering::Lsb, true, Some(start_bit)) = (
+                bit_numbering,
+                attributes.use_big_endian,
+                attributes.start_bit,
+            ) {
+                let Some(length) = attributes.length else {
+                    return Err(Error::custom_at(
+                        "A big endian start_bit counted from the lsb needs a length",
+                        span,
+                    ));
+                };
+                let Some(start_bit) = msb_start_bit(start_bit, length) else {
+                    return Err(Error::custom_at(
+                        "Invalid start_bit, the signal runs off the front of the frame",
+                        span,
+                    ));
+                };
+                attributes.start_bit = Some(start_bit);
+            }
+
+            if attributes.offset.is_none() && attributes.start_bit.is_none() {
+                return Err(Error::Custom {
+                    error: "Did not add an offset for struct member".into(),
+                    span: Some(span),
+                });
+            }
+            // Fast packets reassemble up to 223 bytes, UDS records are as long as the response
+            // and everything else is a single frame
+            if let Some(max_len) = self.attributes.max_len() {
+                let invalid = |what: &str, limit: u16| match self.attributes.fast_packet {
+                    true => format!("Invalid {}, a fast packet is at most 223 bytes", what),
+                    false => format!("Invalid {}, must be less than {}", what, limit),
+                };
+                if attributes
+                    .offset
+                    .is_some_and(|offset| offset as u16 >= max_len)
+                {
+                    return Err(Error::custom_at(invalid("offset", max_len), span));
+                }
+                if attributes
+                    .start_bit
+                    .is_some_and(|start_bit| start_bit >= max_len * 8)
                 {
-                    where_constraints.clear();
-                    where_constraints
-                        .push_parsed_constraint(bounds)
-                        .map_err(|e| e.with_span(lit.span()))?;
-                } else {
-                    for g in generics.iter_generics() {
-                        where_constraints
-                            .push_constraint(g, format!("{}::CanDecode", crate_name))
-                            .unwrap();
+                    return Err(Error::custom_at(invalid("start_bit", max_len * 8), span));
+                }
+            }
+            if let Some((num, den)) = attributes.factor {
+                if attributes.codec.is_some() {
+                    return Err(Error::custom_at(
+                        "Use either codec or factor, not both",
+                        span,
+                    ));
+                }
+                attributes.codec = Some(format!(
+                    "{}::codec::Scaled<{}, {}>",
+                    self.attributes.crate_name, num, den
+                ));
+            }
+            let uom = match uom_quantity(&value_type) {
+                Some((storage, module)) => {
+                    let Some(unit) = &attributes.unit else {
+                        return Err(Error::custom_at(
+                            "A uom quantity needs the unit its signal is sent in, as unit = \"..\"",
+                            span,
+                        ));
+                    };
+                    if attributes.codec.is_none() {
+                        attributes.codec = Some(format!(
+                            "{}::codec::Scaled<1, 1>",
+                            self.attributes.crate_name
+                        ));
                     }
+                    Some(UomQuantity {
+                        storage,
+                        unit: format!("{}::{}", module, unit),
+                    })
                 }
-                Ok(())
-            })?
-            .generate_fn("from_socketcan")
+                None => None,
+            };
+            if attributes.codec.is_some() && attributes.use_decoder.is_some() {
+                return Err(Error::custom_at(
+                    "Use either codec or use_decoder, not both",
+                    span,
+                ));
+            }
+            if (attributes.min.is_some() || attributes.max.is_some())
+                && !matches!(
+                    value_type.as_str(),
+                    "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64" | "f32" | "f64"
+                )
+            {
+                return Err(Error::custom_at(
+                    "min and max are physical values, so need a numeric member",
+                    span,
+                ));
+            }
+            let range_policy = attributes
+                .out_of_range
+                .unwrap_or(self.attributes.out_of_range);
+            let generic = self.generic_types.contains(&value_type);
+            if attributes.start_bit.is_some() {
+                check_bit_member(span, &value_type, generic, &attributes)?;
+            }
+            check_wrapped_member(span, wrapper, &value_type, generic, &attributes)?;
+
+            infos.push(FieldInfo {
+                member,
+                span,
+                type_str,
+                value_type,
+                wrapper,
+                attributes,
+                range_policy,
+                uom,
+            });
+        }
+        Ok(infos)
+    }
+
+    pub fn generate_encode(self, generator: &mut Generator) -> Result<()> {
+        let fields = self.field_infos()?;
+        // Records longer than a frame are only read from slices
+        if self.attributes.single_frame() {
+            self.generate_decode(generator, &fields, DecodeTrait::Frame)?;
+        }
+        self.generate_decode(generator, &fields, DecodeTrait::Slice)
+    }
+
+    /// Lets NMEA 2000 messages tagged `fast_packet` be registered with the reassembler
+    pub fn generate_fast_packet(&self, generator: &mut Generator) -> Result<()> {
+        if !self.attributes.fast_packet {
+            return Ok(());
+        }
+        self.impl_for(
+            generator,
+            format!("{}::nmea2000::FastPacket", self.attributes.crate_name),
+            self.attributes.bounds.as_ref(),
+            None,
+        )?;
+        Ok(())
+    }
+
+    /// Implements one of the decode traits, reading every member out of `frame`
+    fn generate_decode(
+        &self,
+        generator: &mut Generator,
+        fields: &[FieldInfo],
+        decode: DecodeTrait,
+    ) -> Result<()> {
+        let crate_name = &self.attributes.crate_name;
+        let (trait_name, fn_name, frame_type) = match decode {
+            DecodeTrait::Frame => ("CanDecode", "from_socketcan", "[u8; 8]"),
+            DecodeTrait::Slice => ("CanDecodeSlice", "from_slice", "&[u8]"),
+        };
+        let bounds = (self.attributes.decode_bounds.as_ref()).or(self.attributes.bounds.as_ref());
+        let mut impl_for = self.impl_for(
+            generator,
+            format!("{}::{}", crate_name, trait_name),
+            bounds,
+            None,
+        )?;
+        if decode == DecodeTrait::Slice {
+            impl_for
+                .generate_const("MIN_LEN", "usize")
+                .with_value(|value| {
+                    value.push_parsed(min_len_expr(fields, crate_name))?;
+                    Ok(())
+                })?;
+            // MIN_LEN can hang off the member types, so a dlc too short for it is caught here
+            let dlc = match self.attributes.dlc {
+                Some(dlc) => format!(
+                    "{{ assert!({1} >= <Self as {0}::CanDecodeSlice>::MIN_LEN, \"Invalid dlc, the members don't fit in it\"); Some({1}) }}",
+                    crate_name, dlc
+                ),
+                None => "None".to_string(),
+            };
+            impl_for
+                .generate_const("DLC", "Option<usize>")
+                .with_value(|value| {
+                    value.push_parsed(dlc)?;
+                    Ok(())
+                })?;
+        }
+        impl_for
+            .generate_fn(fn_name)
             .with_self_arg(virtue::generate::FnSelfArg::None)
-            .with_arg("frame", "[u8; 8]")
+            .with_arg("frame", frame_type)
             .with_return_type(format!("core::result::Result<Self, {}::Error>", crate_name))
             .body(|fn_body| {
+                if decode == DecodeTrait::Slice {
+                    fn_body.push_parsed(format!(
+                        "{0}::helper::check_len(frame, <Self as {0}::CanDecodeSlice>::MIN_LEN, <Self as {0}::CanDecodeSlice>::DLC)?;",
+                        crate_name
+                    ))?;
+                }
                 fn_body.ident_str("Ok");
                 fn_body.group(Delimiter::Parenthesis, |ok_group| {
                     ok_group.ident_str("Self");
                     ok_group.group(Delimiter::Brace, |struct_body| {
-                        if let Some(fields) = self.fields.as_ref() {
-                            let Fields::Struct(fields) = fields else {
-                                return Err(Error::Custom { error: "Can't use unnamed members. Use a named element struct".into(), span: None });
-                            };
-                            for (ident, field) in fields {
-                                let attributes = field
-                                    .attributes
-                                    .get_attribute::<FieldAttributes>()?
-                                    .unwrap_or_default();
-
-                                let Some(offset) = attributes.offset else {
-                                    return Err(Error::Custom { error: "Did not add an offset for struct member".into(), span: Some(ident.span()) });
-                                };
-
-                                let type_str = field.type_string();
-
-                                let advance_token = match attributes.extract_bytes {
-                                    Some(extract) => {
-                                        format!("{0}::helper::extract_offset_by({1}, &frame, {2})", crate_name, offset, extract)
-                                    }
-                                    None => {
-                                        format!("{0}::helper::extract_offset::<{2}>({1}, &frame)", crate_name, offset, type_str)
-                                    }
-                                };
-
-                                if let Some(decoder) = attributes.use_decoder {
-                                    struct_body
-                                    .push_parsed(
-                                        format!(
-                                        "{1}: {3}( {2}?.try_into().map_err(|_| {0}::Error::InvalidBytesConversion)?,)?,",
-                                        crate_name,
-                                        ident.to_string(),
-                                        advance_token,
-                                        decoder,
-                                    ))?;
-                                }
-                                else if attributes.use_big_endian {
-                                    struct_body
-                                    .push_parsed(format!(
-                                        "{1}: {3}::from_be_bytes( {2}?.try_into().map_err(|_| {0}::Error::InvalidBytesConversion)?,),",
-                                        crate_name,
-                                        ident.to_string(),
-                                        advance_token,
-                                        type_str,
-                                    ))?;
-                                }
-                                else {
-                                    struct_body
-                                    .push_parsed(format!(
-                                        "{1}: {3}::from_le_bytes( {2}?.try_into().map_err(|_| {0}::Error::InvalidBytesConversion)?,),",
-                                        crate_name,
-                                        ident.to_string(),
-                                        advance_token,
-                                        type_str,
-                                    ))?;
-                                }
-
-                                // fn_body.push_parsed(format!(
-                                //     "{}::Encode::encode(&self.{}, encoder)?;",
-                                //     crate_name, field
-                                // ))?;
-                                // if attributes.with_serde {
-                                //     fn_body.push_parsed(format!(
-                                //         "{0}::Encode::encode(&{0}::serde::Compat(&self.{1}), encoder)?;",
-                                //         crate_name, field
-                                //     ))?;
-                                // } else {
-                                //     fn_body.push_parsed(format!(
-                                //         "{}::Encode::encode(&self.{}, encoder)?;",
-                                //         crate_name, field
-                                //     ))?;
-                                // }
-                            }
+                        for field in fields {
+                            struct_body.push_parsed(format!(
+                                "{}: {},",
+                                field.member,
+                                field.decode_expr(crate_name)
+                            ))?;
                         }
-
                         Ok(())
                     })?;
                     Ok(())
@@ -125,154 +348,1166 @@ impl DeriveStruct {
         Ok(())
     }
 
-    pub fn generate_decode(self, generator: &mut Generator) -> Result<()> {
-        // Remember to keep this mostly in sync with generate_borrow_decode
+    /// Lays each member back out into a zeroed frame, the inverse of generate_encode
+    pub fn generate_can_encode(self, generator: &mut Generator) -> Result<()> {
         let crate_name = &self.attributes.crate_name;
-
-        generator
-            .impl_for(format!("{}::Decode", crate_name))
-            .modify_generic_constraints(|generics, where_constraints| {
-                if let Some((bounds, lit)) =
-                    (self.attributes.decode_bounds.as_ref()).or(self.attributes.bounds.as_ref())
-                {
-                    where_constraints.clear();
-                    where_constraints
-                        .push_parsed_constraint(bounds)
-                        .map_err(|e| e.with_span(lit.span()))?;
-                } else {
-                    for g in generics.iter_generics() {
-                        where_constraints
-                            .push_constraint(g, format!("{}::Decode", crate_name))
-                            .unwrap();
-                    }
-                }
-                Ok(())
-            })?
-            .generate_fn("decode")
-            .with_generic_deps("__D", [format!("{}::de::Decoder", crate_name)])
-            .with_arg("decoder", "&mut __D")
+        let fields = self.field_infos()?;
+        let bounds = (self.attributes.encode_bounds.as_ref()).or(self.attributes.bounds.as_ref());
+        let encode_stmts = fields
+            .iter()
+            .map(|field| field.encode_stmt(crate_name))
+            .collect::<Result<String>>()?;
+        // Records longer than a frame are only written out as slices
+        if self.attributes.single_frame() {
+            self.impl_for(
+                generator,
+                format!("{}::CanEncode", crate_name),
+                bounds,
+                None,
+            )?
+            .generate_fn("to_socketcan")
+            .with_self_arg(virtue::generate::FnSelfArg::RefSelf)
             .with_return_type(format!(
-                "core::result::Result<Self, {}::error::DecodeError>",
+                "core::result::Result<[u8; 8], {}::Error>",
                 crate_name
             ))
             .body(|fn_body| {
-                // Ok(Self {
-                fn_body.ident_str("Ok");
-                fn_body.group(Delimiter::Parenthesis, |ok_group| {
-                    ok_group.ident_str("Self");
-                    ok_group.group(Delimiter::Brace, |struct_body| {
-                        // Fields
-                        // {
-                        //      a: proc_macro_crate::Decode::decode(decoder)?,
-                        //      b: proc_macro_crate::Decode::decode(decoder)?,
-                        //      ...
-                        // }
-                        if let Some(fields) = self.fields.as_ref() {
-                            for field in fields.names() {
-                                let attributes = field
-                                    .attributes()
-                                    .get_attribute::<FieldAttributes>()?
-                                    .unwrap_or_default();
-                                // if attributes.with_serde {
-                                //     struct_body
-                                //         .push_parsed(format!(
-                                //             "{1}: (<{0}::serde::Compat<_> as {0}::Decode>::decode(decoder)?).0,",
-                                //             crate_name,
-                                //             field
-                                //         ))?;
-                                // } else {
-                                //     struct_body
-                                //         .push_parsed(format!(
-                                //             "{1}: {0}::Decode::decode(decoder)?,",
-                                //             crate_name,
-                                //             field
-                                //         ))?;
-                                // }
-                            }
-                        }
-                        Ok(())
-                    })?;
-                    Ok(())
-                })?;
+                fn_body.push_parsed("let mut frame = [0u8; 8];")?;
+                fn_body.push_parsed(&encode_stmts)?;
+                fn_body.push_parsed("Ok(frame)")?;
                 Ok(())
             })?;
-        self.generate_borrow_decode(generator)?;
+        }
+
+        // Payloads are as long as the declared DLC, else just long enough for every member
+        let len = match self.attributes.dlc {
+            Some(dlc) => dlc.to_string(),
+            None => min_len_expr(&fields, crate_name),
+        };
+        self.impl_for(
+            generator,
+            format!("{}::CanEncodeSlice", crate_name),
+            bounds,
+            None,
+        )?
+        .generate_fn("to_vec")
+        .with_self_arg(virtue::generate::FnSelfArg::RefSelf)
+        .with_return_type(format!(
+            "core::result::Result<Vec<u8>, {}::Error>",
+            crate_name
+        ))
+        .body(|fn_body| {
+            fn_body.push_parsed(format!("let mut frame = vec![0u8; {}];", len))?;
+            fn_body.push_parsed(&encode_stmts)?;
+            fn_body.push_parsed("Ok(frame)")?;
+            Ok(())
+        })?;
         Ok(())
     }
 
-    pub fn generate_borrow_decode(self, generator: &mut Generator) -> Result<()> {
-        // Remember to keep this mostly in sync with generate_decode
-        let crate_name = self.attributes.crate_name;
+    /// Builds a proptest strategy for the whole struct out of one strategy per member
+    pub fn generate_arbitrary(&self, generator: &mut Generator) -> Result<()> {
+        if !self.attributes.arbitrary {
+            return Ok(());
+        }
+        let crate_name = &self.attributes.crate_name;
+        let fields = self.field_infos()?;
 
-        generator
-            .impl_for_with_lifetimes(format!("{}::BorrowDecode", crate_name), ["__de"])
-            .modify_generic_constraints(|generics, where_constraints| {
-                if let Some((bounds, lit)) = (self.attributes.borrow_decode_bounds.as_ref())
-                    .or(self.attributes.bounds.as_ref())
-                {
-                    where_constraints.clear();
-                    where_constraints
-                        .push_parsed_constraint(bounds)
-                        .map_err(|e| e.with_span(lit.span()))?;
-                } else {
-                    for g in generics.iter_generics() {
-                        where_constraints
-                            .push_constraint(g, format!("{}::de::BorrowDecode<'__de>", crate_name))
-                            .unwrap();
-                    }
-                    for lt in generics.iter_lifetimes() {
-                        where_constraints
-                            .push_parsed_constraint(format!("'__de: '{}", lt.ident))?;
-                    }
-                }
+        // Nest the member strategies as (a, (b, (c, Just(())))) so we aren't limited by tuple arity
+        let mut strategies = format!("{}::proptest::strategy::Just(())", crate_name);
+        let mut pattern = String::from("_");
+        for field in fields.iter().rev() {
+            let mut strategy = field.strategy_expr(crate_name);
+            if field.is_plain() && self.generic_types.contains(&field.value_type) {
+                // A generic could be a float, whose NaN never compares equal to itself
+                strategy = format!(
+                    "{0}::proptest::strategy::Strategy::prop_filter({1}, \"not equal to itself\", |value| core::cmp::PartialEq::eq(value, value))",
+                    crate_name, strategy,
+                );
+            }
+            strategies = format!("({}, {})", strategy, strategies);
+            pattern = format!("({}, {})", field.binding(), pattern);
+        }
+        let names = fields
+            .iter()
+            .map(|field| format!("{}: {}", field.member, field.binding()))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        // Arbitrary values need to be Debug, generic ones comparable to filter out NaN, and boxed
+        // strategies only hold 'static ones. They're made by decoding, so take the decode bounds
+        let bounds = (self.attributes.decode_bounds.as_ref()).or(self.attributes.bounds.as_ref());
+        let mut impl_for = self.impl_for(
+            generator,
+            format!("{}::proptest::arbitrary::Arbitrary", crate_name),
+            bounds,
+            Some("core::fmt::Debug + core::cmp::PartialEq + 'static"),
+        )?;
+        impl_for.modify_generic_constraints(|generics, where_constraints| {
+            for lifetime in generics.iter_lifetimes() {
+                where_constraints
+                    .push_parsed_constraint(format!("'{}: 'static", lifetime.ident))?;
+            }
+            Ok(())
+        })?;
+        impl_for.impl_type("Parameters", "()")?;
+        impl_for.impl_type(
+            "Strategy",
+            format!("{}::proptest::strategy::BoxedStrategy<Self>", crate_name),
+        )?;
+        impl_for
+            .generate_fn("arbitrary_with")
+            .with_arg("_args", "Self::Parameters")
+            .with_return_type("Self::Strategy")
+            .body(|fn_body| {
+                fn_body.push_parsed(format!(
+                    "{0}::proptest::strategy::Strategy::boxed({0}::proptest::strategy::Strategy::prop_map({1}, |{2}| Self {{ {3} }}))",
+                    crate_name, strategies, pattern, names,
+                ))?;
                 Ok(())
-            })?
-            .generate_fn("borrow_decode")
-            .with_generic_deps("__D", [format!("{}::de::BorrowDecoder<'__de>", crate_name)])
-            .with_arg("decoder", "&mut __D")
-            .with_return_type(format!(
-                "core::result::Result<Self, {}::error::DecodeError>",
-                crate_name
-            ))
+            })?;
+        Ok(())
+    }
+
+    /// Serialises each member as a signal, carrying its unit and raw value when asked for
+    /// Raw values are read back out of the encoded payload, so those need CanEncode derived too
+    pub fn generate_serialize(&self, generator: &mut Generator) -> Result<()> {
+        let crate_name = &self.attributes.crate_name;
+        if !self.attributes.serialize {
+            return Ok(());
+        }
+        let fields = self.field_infos()?;
+        let struct_name = generator.target_name().to_string();
+        let raw_fields = fields.iter().filter(|field| field.attributes.serialize_raw);
+        for field in raw_fields.clone() {
+            if field.attributes.use_decoder.is_some() && field.attributes.use_encoder.is_none() {
+                return Err(Error::custom_at(
+                    "serialize_raw reads the raw value back out of the encoded payload, so needs a use_encoder too",
+                    field.span,
+                ));
+            }
+        }
+        // Spanned on the first raw member, so a struct without CanEncode derived is reported there
+        let raw_span = raw_fields.map(|field| field.span).next();
+        let to_ser_error = format!(
+            "map_err(<__S::Error as {}::serde::ser::Error>::custom)?",
+            crate_name
+        );
+
+        self.impl_for(
+            generator,
+            format!("{}::serde::Serialize", crate_name),
+            self.attributes.bounds.as_ref(),
+            Some(&format!("{}::serde::Serialize", crate_name)),
+        )?
+        .generate_fn("serialize")
+            .with_generic_deps("__S", [format!("{}::serde::Serializer", crate_name)])
+            .with_self_arg(virtue::generate::FnSelfArg::RefSelf)
+            .with_arg("serializer", "__S")
+            .with_return_type("core::result::Result<__S::Ok, __S::Error>")
             .body(|fn_body| {
-                // Ok(Self {
-                fn_body.ident_str("Ok");
-                fn_body.group(Delimiter::Parenthesis, |ok_group| {
-                    ok_group.ident_str("Self");
-                    ok_group.group(Delimiter::Brace, |struct_body| {
-                        if let Some(fields) = self.fields.as_ref() {
-                            for field in fields.names() {
-                                let attributes = field
-                                    .attributes()
-                                    .get_attribute::<FieldAttributes>()?
-                                    .unwrap_or_default();
-
-                                // format!("{1}: ")
-                                // struct_body.push_parsed(item)
-
-                                // if attributes.with_serde {
-                                //     struct_body
-                                //         .push_parsed(format!(
-                                //             "{1}: (<{0}::serde::BorrowCompat<_> as {0}::BorrowDecode>::borrow_decode(decoder)?).0,",
-                                //             crate_name,
-                                //             field
-                                //         ))?;
-                                // } else {
-                                //     struct_body
-                                //         .push_parsed(format!(
-                                //             "{1}: {0}::BorrowDecode::borrow_decode(decoder)?,",
-                                //             crate_name,
-                                //             field
-                                //         ))?;
-                                // }
-                            }
-                        }
-                        Ok(())
-                    })?;
-                    Ok(())
-                })?;
+                if let Some(span) = raw_span {
+                    let mut to_vec = StreamBuilder::new();
+                    to_vec.push_parsed(format!("{}::CanEncodeSlice::to_vec(self)", crate_name))?;
+                    to_vec.set_span_on_all_tokens(span);
+                    fn_body.push_parsed("let frame =")?;
+                    fn_body.append(to_vec);
+                    fn_body.push_parsed(format!(".{};", to_ser_error))?;
+                }
+                // Tuple struct members have no names to serialise under
+                let (serialize, state_trait) = match self.is_tuple() {
+                    true => ("serialize_tuple_struct", "SerializeTupleStruct"),
+                    false => ("serialize_struct", "SerializeStruct"),
+                };
+                fn_body.push_parsed(format!(
+                    "let mut state = {0}::serde::Serializer::{1}(serializer, \"{2}\", {3})?;",
+                    crate_name,
+                    serialize,
+                    struct_name,
+                    fields.len(),
+                ))?;
+                for field in &fields {
+                    let unit = match &field.attributes.unit {
+                        Some(unit) => format!("Some({:?})", unit),
+                        None => "None".to_string(),
+                    };
+                    let raw = if field.attributes.serialize_raw {
+                        format!("Some({}.{})", field.raw_expr(crate_name), to_ser_error)
+                    } else {
+                        "None".to_string()
+                    };
+                    let key = match self.is_tuple() {
+                        true => String::new(),
+                        false => format!("\"{}\", ", field.member),
+                    };
+                    fn_body.push_parsed(format!(
+                        "{0}::serde::ser::{1}::serialize_field(&mut state, {2}&{0}::serialize::SignalValue {{ value: &self.{3}, unit: {4}, raw: {5} }})?;",
+                        crate_name, state_trait, key, field.member, unit, raw,
+                    ))?;
+                }
+                fn_body.push_parsed(format!(
+                    "{}::serde::ser::{}::end(state)",
+                    crate_name, state_trait
+                ))?;
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// Describes the message and where each member sits, for tooling to read at runtime
+    pub fn generate_layout(&self, generator: &mut Generator) -> Result<()> {
+        let crate_name = &self.attributes.crate_name;
+        let fields = self.field_infos()?;
+        let name = match &self.attributes.name {
+            Some(name) => name.clone(),
+            None => generator.target_name().to_string(),
+        };
+        let signals = fields
+            .iter()
+            .map(|field| field.signal_info_expr(crate_name))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut impl_for = self.impl_for(
+            generator,
+            format!("{}::CanLayout", crate_name),
+            self.attributes.bounds.as_ref(),
+            None,
+        )?;
+        impl_for
+            .generate_const("ID", "Option<u32>")
+            .with_value(|value| {
+                value.push_parsed(format!("{:?}", self.attributes.can_id()))?;
+                Ok(())
+            })?;
+        impl_for
+            .generate_const("NAME", "&'static str")
+            .with_value(|value| {
+                value.push_parsed(format!("{:?}", name))?;
+                Ok(())
+            })?;
+        impl_for
+            .generate_const("DLC", "Option<u8>")
+            .with_value(|value| {
+                value.push_parsed(format!("{:?}", self.attributes.dlc))?;
                 Ok(())
             })?;
+        impl_for
+            .generate_const("SIGNALS", format!("&'static [{}::SignalInfo]", crate_name))
+            .with_value(|value| {
+                value.push_parsed(format!("&[{}]", signals))?;
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// Sets a bit per member outside its `min` and `max`, indexed as in CanLayout::SIGNALS
+    pub fn generate_validate(&self, generator: &mut Generator) -> Result<()> {
+        let crate_name = &self.attributes.crate_name;
+        let fields = self.field_infos()?;
+        let mut checks = String::new();
+        for (index, field) in fields.iter().enumerate() {
+            let (min, max) = (field.attributes.min, field.attributes.max);
+            if min.is_none() && max.is_none() {
+                continue;
+            }
+            if index >= 64 {
+                return Err(Error::custom_at(
+                    "Only the first 64 members can have a min or max",
+                    field.span,
+                ));
+            }
+            checks += &format!(
+                "if let Some(value) = {} {{ if {}::helper::out_of_range(value, {:?}, {:?}).is_some() {{ bits |= 1 << {}; }} }}",
+                field.physical_expr(),
+                crate_name,
+                min,
+                max,
+                index,
+            );
+        }
+        let body = match checks.is_empty() {
+            true => "0".to_string(),
+            false => format!("let mut bits = 0; {} bits", checks),
+        };
+
+        self.impl_for(
+            generator,
+            format!("{}::CanValidate", crate_name),
+            self.attributes.bounds.as_ref(),
+            None,
+        )?
+        .generate_fn("out_of_range")
+        .with_self_arg(virtue::generate::FnSelfArg::RefSelf)
+        .with_return_type("u64")
+        .body(|fn_body| {
+            fn_body.push_parsed(body)?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Generates a `<Name>View<'a>` over a borrowed frame, decoding each member on demand
+    pub fn generate_view(&self, generator: &mut Generator) -> Result<()> {
+        if !self.attributes.view {
+            return Ok(());
+        }
+        if generator.generics().is_some() {
+            return Err(Error::custom("Can't generate a view for a generic struct"));
+        }
+
+        let crate_name = &self.attributes.crate_name;
+        let fields = self.field_infos()?;
+        let name = generator.target_name();
+        let vis = match self.visibility {
+            Visibility::Pub => "pub ",
+            _ => "",
+        };
+
+        let mut accessors = String::new();
+        for field in &fields {
+            accessors += &format!(
+                "/// Decodes `{1}` out of the frame\npub fn {4}(&self) -> core::result::Result<{2}, {0}::Error> {{ let frame = self.frame; let value = {3}; Ok(value) }}\n",
+                crate_name,
+                field.member,
+                field.type_str,
+                field.decode_expr(crate_name),
+                field.binding(),
+            );
+        }
+
+        let mut view = StreamBuilder::new();
+        view.push_parsed(format!(
+            "/// Borrows a {1} frame, decoding members only when they're asked for
+            #[derive(Clone, Copy, Debug)]
+            {0}struct {1}View<'a> {{ frame: &'a [u8] }}
+            impl<'a> {1}View<'a> {{
+                pub fn new(frame: &'a [u8]) -> Self {{ Self {{ frame }} }}
+                {2}
+            }}",
+            vis, name, accessors,
+        ))?;
+        generator.append(view);
         Ok(())
     }
+
+    /// Emits the E2E config for messages declaring an `e2e_profile`
+    pub fn generate_e2e(&self, generator: &mut Generator) -> Result<()> {
+        let crate_name = &self.attributes.crate_name;
+        let e2e = format!("{}::e2e", crate_name);
+        let profile = match self.attributes.e2e_profile {
+            Some(1) => format!(
+                "{0}::Profile::Profile1 {{ data_id: {1}, mode: {0}::DataIdMode::{2} }}",
+                e2e,
+                self.attributes.e2e_data_id.unwrap_or_default(),
+                self.attributes
+                    .e2e_data_id_mode
+                    .as_deref()
+                    .unwrap_or("Both"),
+            ),
+            Some(_) => format!(
+                "{0}::Profile::Profile2 {{ data_id_list: {1:?} }}",
+                e2e,
+                self.attributes.e2e_data_id_list.clone().unwrap_or_default(),
+            ),
+            None => return Ok(()),
+        };
+
+        self.impl_for(
+            generator,
+            format!("{}::E2eProtected", e2e),
+            self.attributes.bounds.as_ref(),
+            None,
+        )?
+        .generate_const("E2E", format!("{}::Config", e2e))
+        .with_value(|value| {
+            value.push_parsed(format!(
+                "{0}::Config {{ profile: {1}, max_delta_counter: {2} }}",
+                e2e,
+                profile,
+                self.attributes.e2e_max_delta_counter.unwrap_or(1),
+            ))?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Lets messages declaring a `pgn` be matched on J1939 IDs
+    pub fn generate_j1939(&self, generator: &mut Generator) -> Result<()> {
+        let Some(pgn) = self.attributes.pgn else {
+            return Ok(());
+        };
+        self.impl_for(
+            generator,
+            format!("{}::j1939::J1939Message", self.attributes.crate_name),
+            self.attributes.bounds.as_ref(),
+            None,
+        )?
+        .generate_const("PGN", "u32")
+        .with_value(|value| {
+            value.push_parsed(format!("{:#X}", pgn))?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Lets records declaring a `did` be read out of UDS ReadDataByIdentifier responses
+    pub fn generate_uds(&self, generator: &mut Generator) -> Result<()> {
+        let Some(did) = self.attributes.did else {
+            return Ok(());
+        };
+        self.impl_for(
+            generator,
+            format!("{}::uds::DataIdentifier", self.attributes.crate_name),
+            self.attributes.bounds.as_ref(),
+            None,
+        )?
+        .generate_const("DID", "u16")
+        .with_value(|value| {
+            value.push_parsed(format!("{:#X}", did))?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Lets messages declaring a `pdo` be matched on their node's COB-ID
+    pub fn generate_canopen(&self, generator: &mut Generator) -> Result<()> {
+        let (Some(pdo), Some(node)) = (self.attributes.pdo, self.attributes.node) else {
+            return Ok(());
+        };
+        let mut impl_for = self.impl_for(
+            generator,
+            format!("{}::canopen::Pdo", self.attributes.crate_name),
+            self.attributes.bounds.as_ref(),
+            None,
+        )?;
+        impl_for
+            .generate_const("NUMBER", "u8")
+            .with_value(|value| {
+                value.push_parsed(pdo.to_string())?;
+                Ok(())
+            })?;
+        impl_for
+            .generate_const("TRANSMIT", "bool")
+            .with_value(|value| {
+                value.push_parsed((!self.attributes.rpdo).to_string())?;
+                Ok(())
+            })?;
+        impl_for.generate_const("NODE", "u8").with_value(|value| {
+            value.push_parsed(format!("{:#04X}", node))?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Lets PIDs declaring a `pid` be read out of OBD-II service 01 and 02 responses
+    pub fn generate_obd2(&self, generator: &mut Generator) -> Result<()> {
+        let Some(pid) = self.attributes.pid else {
+            return Ok(());
+        };
+        self.impl_for(
+            generator,
+            format!("{}::obd2::Pid", self.attributes.crate_name),
+            self.attributes.bounds.as_ref(),
+            None,
+        )?
+        .generate_const("PID", "u8")
+        .with_value(|value| {
+            value.push_parsed(format!("{:#04X}", pid))?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+impl FieldInfo {
+    fn offset(&self) -> u8 {
+        self.attributes.offset.unwrap_or_default()
+    }
+
+    /// What a member whose type is a generic parameter needs, as a where clause
+    /// Decoders are plain functions, so they carry their own bounds
+    fn generic_bound(&self, crate_name: &str) -> Option<String> {
+        if self.attributes.use_decoder.is_some() {
+            None
+        } else if let Some(codec) = &self.attributes.codec {
+            Some(format!(
+                "{}: {}::FieldCodec<{}>",
+                codec, crate_name, self.value_type
+            ))
+        } else if self.wrapper.is_some() {
+            Some(format!(
+                "{}: {}::helper::RawBits",
+                self.value_type, crate_name
+            ))
+        } else {
+            Some(format!("{}: {}::CanField", self.value_type, crate_name))
+        }
+    }
+
+    /// A name to bind the member to, `field_0` and on for tuple struct members
+    fn binding(&self) -> String {
+        match self.member.parse::<usize>() {
+            Ok(index) => format!("field_{}", index),
+            Err(_) => self.member.clone(),
+        }
+    }
+
+    /// One past the last byte the member touches, as a const expression
+    fn end_expr(&self, crate_name: &str) -> String {
+        let Some(start_bit) = self.attributes.start_bit else {
+            return format!("{} + {}", self.offset(), self.byte_len_expr(crate_name));
+        };
+        let length = self.attributes.length.unwrap_or_default().max(1);
+        let last_bit = if self.attributes.use_big_endian {
+            // Motorola start bits point at the MSB, straighten the sawtooth out first
+            (start_bit / 8) * 8 + (7 - start_bit % 8) + length - 1
+        } else {
+            start_bit + length - 1
+        };
+        (last_bit / 8 + 1).to_string()
+    }
+
+    /// Whether the member is read through CanField, having no wrapper, codec or decoder
+    fn is_plain(&self) -> bool {
+        self.wrapper.is_none()
+            && self.attributes.codec.is_none()
+            && self.attributes.use_decoder.is_none()
+    }
+
+    /// Length of the member in bytes, as a const expression
+    fn byte_len_expr(&self, crate_name: &str) -> String {
+        match self.attributes.extract_bytes {
+            Some(extract) => extract.to_string(),
+            None if self.is_plain() => {
+                format!("({} as usize).div_ceil(8)", self.length_expr(crate_name))
+            }
+            None => format!("core::mem::size_of::<{}>()", self.value_type),
+        }
+    }
+
+    /// Length of the member in bits, as a const expression
+    fn length_expr(&self, crate_name: &str) -> String {
+        match (self.attributes.length, self.attributes.extract_bytes) {
+            (Some(length), _) => length.to_string(),
+            (None, Some(extract)) => (extract as u16 * 8).to_string(),
+            (None, None) if self.is_plain() => {
+                format!("<{} as {}::CanField>::BITS", self.value_type, crate_name)
+            }
+            (None, None) => format!("({} * 8) as u16", self.byte_len_expr(crate_name)),
+        }
+    }
+
+    /// DBC start bit of the member - whole byte members start at the LSB of their offset byte
+    /// for Intel, and the MSB for Motorola
+    fn start_bit(&self) -> u16 {
+        match (self.attributes.start_bit, self.attributes.use_big_endian) {
+            (Some(start_bit), _) => start_bit,
+            (None, true) => self.offset() as u16 * 8 + 7,
+            (None, false) => self.offset() as u16 * 8,
+        }
+    }
+
+    /// The member's codec, qualified as a FieldCodec of the member type
+    fn codec_expr(&self, crate_name: &str) -> Option<String> {
+        let codec = self.attributes.codec.as_ref()?;
+        let value_type = match &self.uom {
+            Some(uom) => &uom.storage,
+            None => &self.value_type,
+        };
+        Some(format!(
+            "<{} as {}::FieldCodec<{}>>",
+            codec, crate_name, value_type
+        ))
+    }
+
+    /// Expression decoding `raw` through `codec`, as a Result of the value type
+    fn codec_decode_expr(&self, crate_name: &str, codec: &str, raw: &str) -> String {
+        let decode = format!(
+            "{}::decode({}, {})",
+            codec,
+            raw,
+            self.length_expr(crate_name)
+        );
+        match &self.uom {
+            Some(uom) => format!("{}.map(<{}>::new::<{}>)", decode, self.value_type, uom.unit),
+            None => decode,
+        }
+    }
+
+    /// Expression encoding `value`, a reference to the value type, through `codec` as a
+    /// Result<u64>
+    fn codec_encode_expr(&self, crate_name: &str, codec: &str, value: &str) -> String {
+        let value = match &self.uom {
+            Some(uom) => format!("&({}).get::<{}>()", value, uom.unit),
+            None => value.to_string(),
+        };
+        format!(
+            "{}::encode({}, {})",
+            codec,
+            value,
+            self.length_expr(crate_name)
+        )
+    }
+
+    fn byte_order_expr(&self, crate_name: &str) -> String {
+        match self.attributes.use_big_endian {
+            true => format!("{}::ByteOrder::BigEndian", crate_name),
+            false => format!("{}::ByteOrder::LittleEndian", crate_name),
+        }
+    }
+
+    /// Start bit, length and byte order arguments locating a plain member, for CanField
+    fn field_args(&self, crate_name: &str) -> String {
+        format!(
+            "{}, {}, {}",
+            self.start_bit(),
+            self.length_expr(crate_name),
+            self.byte_order_expr(crate_name)
+        )
+    }
+
+    /// Arguments locating a bit level member, for the helper bit functions
+    fn bits_args(&self, crate_name: &str) -> Option<String> {
+        let start_bit = self.attributes.start_bit?;
+        Some(format!(
+            "{}, {}, {}",
+            start_bit,
+            self.attributes.length.unwrap_or_default(),
+            self.byte_order_expr(crate_name)
+        ))
+    }
+
+    /// SignalInfo literal describing this member
+    fn signal_info_expr(&self, crate_name: &str) -> String {
+        let start_bit = self.start_bit();
+        let (signed, scaling) = if let Some(codec) = self.codec_expr(crate_name) {
+            (format!("{}::SIGNED", codec), format!("{}::SCALING", codec))
+        } else if self.attributes.use_decoder.is_some() {
+            ("false".to_string(), "None".to_string())
+        } else {
+            let signed = matches!(
+                self.value_type.as_str(),
+                "i8" | "i16" | "i32" | "i64" | "i128" | "isize"
+            );
+            (
+                signed.to_string(),
+                format!("Some({}::Scaling::IDENTITY)", crate_name),
+            )
+        };
+        format!(
+            "{0}::SignalInfo {{ name: {1:?}, start_bit: {2}, length: {3}, byte_order: {4}, signed: {5}, scaling: {6}, unit: {7:?}, type_name: {8:?}, spn: {9:?}, min: {10:?}, max: {11:?} }}",
+            crate_name,
+            self.member,
+            start_bit,
+            self.length_expr(crate_name),
+            self.byte_order_expr(crate_name),
+            signed,
+            scaling,
+            self.attributes.unit,
+            self.type_str,
+            self.attributes.spn,
+            self.attributes.min,
+            self.attributes.max,
+        )
+    }
+
+    /// Expression pulling this member's bytes out of `frame`
+    fn extract_expr(&self, crate_name: &str) -> String {
+        format!(
+            "{0}::helper::extract_offset_by({1}, &frame, {2})",
+            crate_name,
+            self.offset(),
+            self.byte_len_expr(crate_name)
+        )
+    }
+
+    /// Mutable version of extract_expr, for encoding
+    fn extract_mut_expr(&self, crate_name: &str) -> String {
+        format!(
+            "{0}::helper::extract_offset_mut_by({1}, &mut frame, {2})",
+            crate_name,
+            self.offset(),
+            self.byte_len_expr(crate_name)
+        )
+    }
+
+    /// Expression reading the raw value of this member out of `frame`, as a Result<u64>
+    fn raw_expr(&self, crate_name: &str) -> String {
+        match self.bits_args(crate_name) {
+            Some(bits) => format!("{0}::helper::extract_bits(&frame, {1})", crate_name, bits),
+            None => format!(
+                "{1}.map(|bytes| {0}::helper::raw_value(bytes, {2}))",
+                crate_name,
+                self.extract_expr(crate_name),
+                self.attributes.use_big_endian
+            ),
+        }
+    }
+
+    /// Statements applying `policy` for the member's `min` and `max` to `value`, a decoded value
+    /// type, or None when there's nothing to do
+    fn range_stmts(&self, crate_name: &str, policy: RangePolicy) -> Option<String> {
+        let (min, max) = (self.attributes.min, self.attributes.max);
+        if min.is_none() && max.is_none() {
+            return None;
+        }
+        match policy {
+            RangePolicy::Error => Some(format!(
+                "{}::helper::check_range({:?}, value as f64, {:?}, {:?})?;",
+                crate_name, self.member, min, max,
+            )),
+            RangePolicy::Clamp => Some(format!(
+                "let value = match {}::helper::out_of_range(value as f64, {:?}, {:?}) {{ Some(bound) => bound as {}, None => value }};",
+                crate_name, min, max, self.value_type,
+            )),
+            RangePolicy::Flag => None,
+        }
+    }
+
+    /// Expression for the member's physical value as an Option<f64>, None when it isn't valid
+    fn physical_expr(&self) -> String {
+        match self.wrapper {
+            Some(Wrapper::Signal) => format!(
+                "Option::<&{}>::from(self.{}.as_ref()).map(|value| *value as f64)",
+                self.value_type, self.member
+            ),
+            Some(Wrapper::Option) => {
+                format!("self.{}.as_ref().map(|value| *value as f64)", self.member)
+            }
+            None => format!("Some(self.{} as f64)", self.member),
+        }
+    }
+
+    /// Expression decoding this member from `frame`
+    fn decode_expr(&self, crate_name: &str) -> String {
+        let range = self.range_stmts(crate_name, self.range_policy);
+        if let Some(wrapper) = self.wrapper {
+            let mut decode_raw = self.decode_raw_expr(crate_name);
+            if let Some(range) = range {
+                decode_raw = format!("{}.and_then(|value| {{ {} Ok(value) }})", decode_raw, range);
+            }
+            let signal = format!(
+                "{}.decode({}?, {}, |raw| {})?",
+                self.ranges_expr(crate_name),
+                self.raw_expr(crate_name),
+                self.length_expr(crate_name),
+                decode_raw,
+            );
+            match wrapper {
+                Wrapper::Signal => signal,
+                Wrapper::Option => format!("Option::<{}>::from({})", self.value_type, signal),
+            }
+        } else {
+            let value = self.decode_value_expr(crate_name);
+            match range {
+                Some(range) => format!("{{ let value = {}; {} value }}", value, range),
+                None => value,
+            }
+        }
+    }
+
+    /// Expression decoding an unwrapped member from `frame`
+    fn decode_value_expr(&self, crate_name: &str) -> String {
+        let extract = self.extract_expr(crate_name);
+        if let Some(codec) = self.codec_expr(crate_name) {
+            let raw = format!("{}?", self.raw_expr(crate_name));
+            format!("{}?", self.codec_decode_expr(crate_name, &codec, &raw))
+        } else if let Some(decoder) = &self.attributes.use_decoder {
+            format!(
+                "{2}( {1}?.try_into().map_err(|_| {0}::Error::InvalidBytesConversion)?,)?",
+                crate_name, extract, decoder
+            )
+        } else {
+            format!(
+                "<{1} as {0}::CanField>::decode(&frame, {2})?",
+                crate_name,
+                self.type_str,
+                self.field_args(crate_name),
+            )
+        }
+    }
+
+    /// How a wrapped member recognises raw values that aren't available
+    fn ranges_expr(&self, crate_name: &str) -> String {
+        let attributes = &self.attributes;
+        match (
+            attributes.not_available,
+            attributes.error,
+            attributes.reserved,
+        ) {
+            (None, None, None) => format!("{}::signal::Ranges::J1939", crate_name),
+            (not_available, error, reserved) => format!(
+                "{}::signal::Ranges::Values {{ not_available: {:?}, error: {:?}, reserved: {:?} }}",
+                crate_name, not_available, error, reserved
+            ),
+        }
+    }
+
+    /// Expression decoding the value type from `raw`, as a Result
+    fn decode_raw_expr(&self, crate_name: &str) -> String {
+        match self.codec_expr(crate_name) {
+            Some(codec) => self.codec_decode_expr(crate_name, &codec, "raw"),
+            None => format!(
+                "Ok(<{} as {}::helper::RawBits>::from_raw(raw, {}))",
+                self.value_type,
+                crate_name,
+                self.length_expr(crate_name)
+            ),
+        }
+    }
+
+    /// Expression encoding `value`, a reference to the value type, as a Result<u64>
+    fn encode_raw_expr(&self, crate_name: &str) -> String {
+        match self.codec_expr(crate_name) {
+            Some(codec) => self.codec_encode_expr(crate_name, &codec, "value"),
+            None => format!("Ok({}::helper::RawBits::to_raw(value))", crate_name),
+        }
+    }
+
+    /// Statement writing a raw value into the member's bits or bytes of `frame`
+    fn insert_raw_stmt(&self, crate_name: &str, raw: &str) -> String {
+        match self.bits_args(crate_name) {
+            Some(bits) => format!(
+                "{0}::helper::insert_bits(&mut frame, {1}, {2})?;",
+                crate_name, bits, raw
+            ),
+            None => format!(
+                "{0}::helper::insert_raw({1}?, {2}, {3})?;",
+                crate_name,
+                self.extract_mut_expr(crate_name),
+                raw,
+                self.attributes.use_big_endian
+            ),
+        }
+    }
+
+    /// Statement writing `self.<member>` into `frame`
+    fn encode_stmt(&self, crate_name: &str) -> Result<String> {
+        let extract = self.extract_mut_expr(crate_name);
+        if let Some(wrapper) = self.wrapper {
+            let signal = match wrapper {
+                Wrapper::Signal => format!("self.{}.as_ref()", self.member),
+                Wrapper::Option => {
+                    format!(
+                        "{}::Signal::from(self.{}.as_ref())",
+                        crate_name, self.member
+                    )
+                }
+            };
+            let raw = format!(
+                "{}.encode({}, {}, |value| {})?",
+                self.ranges_expr(crate_name),
+                signal,
+                self.length_expr(crate_name),
+                self.encode_raw_expr(crate_name),
+            );
+            Ok(self.insert_raw_stmt(crate_name, &raw))
+        } else if let Some(codec) = self.codec_expr(crate_name) {
+            let value = format!("&self.{}", self.member);
+            let raw = format!("{}?", self.codec_encode_expr(crate_name, &codec, &value));
+            Ok(self.insert_raw_stmt(crate_name, &raw))
+        } else if let Some(encoder) = &self.attributes.use_encoder {
+            Ok(format!(
+                "{2}(&self.{1}, {0}?)?;",
+                extract, self.member, encoder
+            ))
+        } else if self.attributes.use_decoder.is_some() {
+            Err(Error::custom_at(
+                "A member with use_decoder needs a use_encoder to be encoded",
+                self.span,
+            ))
+        } else {
+            Ok(format!(
+                "{0}::CanField::encode(&self.{1}, &mut frame, {2})?;",
+                crate_name,
+                self.member,
+                self.field_args(crate_name),
+            ))
+        }
+    }
+
+    /// proptest strategy generating values this member can actually be decoded as
+    fn strategy_expr(&self, crate_name: &str) -> String {
+        // Values outside min and max won't decode as themselves, unless they're only flagged
+        let clamp = match self.range_policy {
+            RangePolicy::Flag => None,
+            _ => self.range_stmts(crate_name, RangePolicy::Clamp),
+        };
+        if let Some(wrapper) = self.wrapper {
+            // Random raw values cover the valid, not available and error ranges alike
+            let into = match wrapper {
+                Wrapper::Signal => String::new(),
+                Wrapper::Option => format!(".map(Option::<{}>::from)", self.value_type),
+            };
+            let mut decode_raw = self.decode_raw_expr(crate_name);
+            if let Some(clamp) = clamp {
+                decode_raw = format!("{}.map(|value| {{ {} value }})", decode_raw, clamp);
+            }
+            format!(
+                "{0}::proptest::strategy::Strategy::prop_filter_map({0}::proptest::arbitrary::any::<u64>(), \"undecodable raw value\", |raw| {1}.decode(raw & (u64::MAX >> (64 - {2})), {2}, |raw| {3}).ok(){4})",
+                crate_name,
+                self.ranges_expr(crate_name),
+                self.length_expr(crate_name),
+                decode_raw,
+                into,
+            )
+        } else {
+            let strategy = self.value_strategy_expr(crate_name);
+            match clamp {
+                Some(clamp) => format!(
+                    "{0}::proptest::strategy::Strategy::prop_map({1}, |value| {{ {2} value }})",
+                    crate_name, strategy, clamp,
+                ),
+                None => strategy,
+            }
+        }
+    }
+
+    /// proptest strategy for an unwrapped member, before any clamping to its range
+    fn value_strategy_expr(&self, crate_name: &str) -> String {
+        if self.attributes.ieee_float {
+            // Every bit pattern decodes, but NaN never compares equal to itself
+            format!(
+                "{0}::proptest::num::{1}::POSITIVE | {0}::proptest::num::{1}::NEGATIVE | {0}::proptest::num::{1}::NORMAL | {0}::proptest::num::{1}::SUBNORMAL | {0}::proptest::num::{1}::ZERO | {0}::proptest::num::{1}::INFINITE",
+                crate_name, self.type_str,
+            )
+        } else if let Some(codec) = self.codec_expr(crate_name) {
+            // Let the codec pick out the raw values it can decode
+            let raw = format!(
+                "raw & (u64::MAX >> (64 - {}))",
+                self.length_expr(crate_name)
+            );
+            format!(
+                "{0}::proptest::strategy::Strategy::prop_filter_map({0}::proptest::arbitrary::any::<u64>(), \"undecodable raw value\", |raw| {1}.ok())",
+                crate_name,
+                self.codec_decode_expr(crate_name, &codec, &raw),
+            )
+        } else if let Some(decoder) = &self.attributes.use_decoder {
+            // Only the decoder knows the value domain, so feed it random bytes of the right size
+            format!(
+                "{0}::proptest::strategy::Strategy::prop_filter_map({0}::proptest::arbitrary::any::<[u8; {1}]>(), \"undecodable bytes\", |bytes| {2}(&bytes).ok())",
+                crate_name,
+                self.byte_len_expr(crate_name),
+                decoder,
+            )
+        } else if self.type_str == "f32" || self.type_str == "f64" {
+            // NaN never compares equal to itself, so leave it out
+            format!(
+                "{0}::proptest::num::{1}::POSITIVE | {0}::proptest::num::{1}::NEGATIVE | {0}::proptest::num::{1}::NORMAL | {0}::proptest::num::{1}::SUBNORMAL | {0}::proptest::num::{1}::ZERO | {0}::proptest::num::{1}::INFINITE",
+                crate_name, self.type_str,
+            )
+        } else {
+            // Decode random bits, giving whatever values the member's bits can hold
+            let start_bit = match self.attributes.use_big_endian {
+                true => 7,
+                false => 0,
+            };
+            format!(
+                "{0}::proptest::strategy::Strategy::prop_filter_map({0}::proptest::collection::vec({0}::proptest::arbitrary::any::<u8>(), {1}), \"undecodable bits\", |frame| <{2} as {0}::CanField>::decode(&frame, {3}, {4}, {5}).ok())",
+                crate_name,
+                self.byte_len_expr(crate_name),
+                self.type_str,
+                start_bit,
+                self.length_expr(crate_name),
+                self.byte_order_expr(crate_name),
+            )
+        }
+    }
+}
+
+/// The furthest byte any member reaches, as an expression worked out at compile time
+fn min_len_expr(fields: &[FieldInfo], crate_name: &str) -> String {
+    if fields.is_empty() {
+        return "0".to_string();
+    }
+    let ends = fields
+        .iter()
+        .map(|field| {
+            format!(
+                "let end = {}; if end > len {{ len = end; }}",
+                field.end_expr(crate_name)
+            )
+        })
+        .collect::<String>();
+    format!("{{ let mut len = 0; {} len }}", ends)
+}
+
+/// Splits `Signal<T>` and `Option<T>` member types into the wrapper and `T`
+fn split_wrapper(type_str: &str) -> (Option<Wrapper>, String) {
+    let compact = type_str.replace(' ', "");
+    let split = compact
+        .strip_suffix('>')
+        .and_then(|generic| generic.split_once('<'));
+    if let Some((path, inner)) = split {
+        match path.rsplit("::").next() {
+            Some("Signal") => return (Some(Wrapper::Signal), inner.to_string()),
+            Some("Option") => return (Some(Wrapper::Option), inner.to_string()),
+            _ => {}
+        }
+    }
+    (None, type_str.to_string())
+}
+
+/// The storage type and unit module of a uom quantity, so `uom::si::f32::Velocity` gives `f32`
+/// and `uom::si::velocity`
+fn uom_quantity(value_type: &str) -> Option<(String, String)> {
+    let compact = value_type.replace(' ', "");
+    let (prefix, rest) = compact.rsplit_once("::si::")?;
+    let (storage, quantity) = rest.split_once("::")?;
+    if !prefix.ends_with("uom") || !matches!(storage, "f32" | "f64") || quantity.contains("::") {
+        return None;
+    }
+    let mut module = String::new();
+    for (index, c) in quantity.chars().enumerate() {
+        if c.is_ascii_uppercase() && index > 0 {
+            module.push('_');
+        }
+        module.push(c.to_ascii_lowercase());
+    }
+    Some((storage.to_string(), format!("{}::si::{}", prefix, module)))
+}
+
+/// Wrapped members are checked on their raw value before decoding, so need a raw value to check
+fn check_wrapped_member(
+    span: Span,
+    wrapper: Option<Wrapper>,
+    value_type: &str,
+    generic: bool,
+    attributes: &FieldAttributes,
+) -> Result<()> {
+    if wrapper.is_none() {
+        if attributes.not_available.is_some()
+            || attributes.error.is_some()
+            || attributes.reserved.is_some()
+        {
+            return Err(Error::custom_at(
+                "not_available, error and reserved need a Signal<T> or Option<T> member",
+                span,
+            ));
+        }
+        return Ok(());
+    }
+    if attributes.use_decoder.is_some() {
+        return Err(Error::custom_at(
+            "A Signal<T> or Option<T> member can't use_decoder, use a codec instead",
+            span,
+        ));
+    }
+    let raw_bits = generic
+        || matches!(
+            value_type,
+            "bool" | "u8" | "i8" | "u16" | "i16" | "u32" | "i32" | "u64" | "i64"
+        );
+    if attributes.codec.is_none() && !raw_bits {
+        return Err(Error::custom_at(
+            "A Signal<T> or Option<T> member needs a codec, or an integer or bool T",
+            span,
+        ));
+    }
+    Ok(())
+}
+
+/// IEEE floats are bit members decoded through the Ieee754 codec, with the length set by the type
+fn apply_ieee_float(
+    span: Span,
+    type_str: &str,
+    crate_name: &str,
+    attributes: &mut FieldAttributes,
+) -> Result<()> {
+    let length = match type_str {
+        "f32" => 32,
+        "f64" => 64,
+        _ => {
+            return Err(Error::custom_at(
+                "An ieee_float member must be an f32 or f64",
+                span,
+            ))
+        }
+    };
+    if attributes.start_bit.is_none() {
+        return Err(Error::custom_at(
+            "ieee_float needs a start_bit, whole byte floats are IEEE already",
+            span,
+        ));
+    }
+    if attributes.codec.is_some() || attributes.use_decoder.is_some() {
+        return Err(Error::custom_at(
+            "ieee_float can't be combined with codec or use_decoder",
+            span,
+        ));
+    }
+    if attributes.length.is_some_and(|declared| declared != length) {
+        return Err(Error::custom_at(
+            format!("An ieee_float {} is {} bits long", type_str, length),
+            span,
+        ));
+    }
+    attributes.length = Some(length);
+    attributes.codec = Some(format!("{}::codec::Ieee754", crate_name));
+    Ok(())
+}
+
+/// DBC start bit of a big endian signal, from the start bit of its least significant bit
+fn msb_start_bit(lsb: u16, length: u16) -> Option<u16> {
+    // Count along the straightened out sawtooth, where bit 0 is the MSB of byte 0
+    let lsb = (lsb / 8) * 8 + (7 - lsb % 8);
+    let msb = lsb.checked_sub(length - 1)?;
+    Some((msb / 8) * 8 + (7 - msb % 8))
+}
+
+/// Bit level members are read through CanField, which the compiler checks - this only catches
+/// lengths that can't fit the primitive types
+fn check_bit_member(
+    span: Span,
+    type_str: &str,
+    generic: bool,
+    attributes: &FieldAttributes,
+) -> Result<()> {
+    let Some(length) = attributes.length else {
+        return Err(Error::custom_at(
+            "A start_bit member also needs a length",
+            span,
+        ));
+    };
+    if attributes.offset.is_some() || attributes.extract_bytes.is_some() {
+        return Err(Error::custom_at(
+            "Use either offset/extract or start_bit/length, not both",
+            span,
+        ));
+    }
+    if attributes.use_decoder.is_some() {
+        return Err(Error::custom_at(
+            "use_decoder works on whole bytes, use offset rather than start_bit",
+            span,
+        ));
+    }
+    if attributes.codec.is_some() || generic {
+        // The codec or the generic's bound decides what it can be decoded into
+        return Ok(());
+    }
+    let type_bits = match type_str {
+        "bool" => 1,
+        "u8" | "i8" => 8,
+        "u16" | "i16" => 16,
+        "u32" | "i32" => 32,
+        "u64" | "i64" => 64,
+        "u128" | "i128" => 128,
+        "f32" => 32,
+        "f64" => 64,
+        _ => return Ok(()),
+    };
+    if type_str.starts_with('f') && length != type_bits {
+        return Err(Error::custom_at(
+            format!("A {} takes exactly {} bits", type_str, type_bits),
+            span,
+        ));
+    }
+    if length > type_bits {
+        return Err(Error::custom_at(
+            format!("A {} can't hold {} bits", type_str, length),
+            span,
+        ));
+    }
+    Ok(())
 }
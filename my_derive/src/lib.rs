@@ -1,6 +1,8 @@
 extern crate proc_macro;
 use proc_macro::TokenStream;
 
+use virtue::generate::Parent;
+use virtue::parse::Visibility;
 use virtue::prelude::*;
 
 mod attribute;
@@ -15,18 +17,35 @@ pub fn derive_encode(input: proc_macro::TokenStream) -> proc_macro::TokenStream
 
 fn derive_encode_inner(input: TokenStream) -> Result<TokenStream> {
     let parse = Parse::new(input)?;
+    let visibility = struct_visibility(&parse);
     let (mut generator, attributes, body) = parse.into_generator();
+    let generic_types = generic_types(&generator);
     let attributes = attributes
         .get_attribute::<ContainerAttributes>()?
         .unwrap_or_default();
 
     match body {
         Body::Struct(body) => {
-            derive_struct::DeriveStruct {
+            let derive = derive_struct::DeriveStruct {
                 fields: body.fields,
                 attributes,
-            }
-            .generate_encode(&mut generator)?;
+                visibility,
+                generic_types,
+            };
+            derive.generate_layout(&mut generator)?;
+            derive.generate_validate(&mut generator)?;
+            derive.generate_view(&mut generator)?;
+            derive.generate_e2e(&mut generator)?;
+            derive.generate_j1939(&mut generator)?;
+            derive.generate_uds(&mut generator)?;
+            derive.generate_obd2(&mut generator)?;
+            derive.generate_canopen(&mut generator)?;
+            derive.generate_fast_packet(&mut generator)?;
+            #[cfg(feature = "proptest")]
+            derive.generate_arbitrary(&mut generator)?;
+            #[cfg(feature = "serde")]
+            derive.generate_serialize(&mut generator)?;
+            derive.generate_encode(&mut generator)?;
         }
         Body::Enum(_body) => {
             // derive_enum::DeriveEnum {
@@ -41,3 +60,56 @@ fn derive_encode_inner(input: TokenStream) -> Result<TokenStream> {
 
     generator.finish()
 }
+
+#[proc_macro_derive(CanEncode, attributes(can_extract))]
+pub fn derive_can_encode(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive_can_encode_inner(input).unwrap_or_else(|e| e.into_token_stream())
+}
+
+fn derive_can_encode_inner(input: TokenStream) -> Result<TokenStream> {
+    let parse = Parse::new(input)?;
+    let visibility = struct_visibility(&parse);
+    let (mut generator, attributes, body) = parse.into_generator();
+    let generic_types = generic_types(&generator);
+    let attributes = attributes
+        .get_attribute::<ContainerAttributes>()?
+        .unwrap_or_default();
+
+    match body {
+        Body::Struct(body) => {
+            derive_struct::DeriveStruct {
+                fields: body.fields,
+                attributes,
+                visibility,
+                generic_types,
+            }
+            .generate_can_encode(&mut generator)?;
+        }
+        Body::Enum(_body) => {}
+    }
+
+    generator.export_to_file("proc_macro_crate", "CanEncode");
+
+    generator.finish()
+}
+
+/// Generated helper types share the visibility of the struct they're derived for
+fn struct_visibility(parse: &Parse) -> Visibility {
+    match parse {
+        Parse::Struct { visibility, .. } | Parse::Enum { visibility, .. } => visibility.clone(),
+        _ => Visibility::Default,
+    }
+}
+
+/// Names of the struct's type parameters, which members can be declared as
+fn generic_types(generator: &Generator) -> Vec<String> {
+    generator
+        .generics()
+        .map(|generics| {
+            generics
+                .iter_generics()
+                .map(|generic| generic.ident.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -3,12 +3,16 @@
 #![allow(unused_mut)]
 #![allow(unused_variables)]
 
+use can_extract::codec::Scaled;
+use can_extract::Signal;
 use core::default::Default;
-use proc_macro_derive_crate::CanDecode;
+use proc_macro_derive_crate::{CanDecode, CanEncode};
 
 /// A simple test struct
 /// This is the guy we want to decode from an 8 byte CAN message
-#[derive(CanDecode, Debug)]
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+// Also generate a TestStructView, which decodes members lazily out of a borrowed frame
+#[can_extract(id = 0x123, dlc = 8, view, arbitrary)]
 struct TestStruct {
     // All items must have an offset, indicating where they start reading in the buffer
     // The reader will extract std::mem::size_of<T> for a struct member, unless told to use an extract = u8 val
@@ -21,7 +25,13 @@ struct TestStruct {
     #[can_extract(offset = 2, use_big_endian)]
     b: u16,
     // Start at byte 6, skipping bytes 4,5 extract 2 bytes for a u16 base value, and convert to f32 via the divide_by_1000 func
-    #[can_extract(offset = 6, extract = 2, use_decoder = "divide_by_1000")]
+    // Encoding goes back the other way through multiply_by_1000
+    #[can_extract(
+        offset = 6,
+        extract = 2,
+        use_decoder = "divide_by_1000",
+        use_encoder = "multiply_by_1000"
+    )]
     c: f32,
 }
 
@@ -40,12 +50,313 @@ fn divide_by_1000(bytes: &[u8]) -> Result<f32, can_extract::Error> {
     Ok((original as f32) / 1000.0)
 }
 
+fn multiply_by_1000(value: &f32, bytes: &mut [u8]) -> Result<(), can_extract::Error> {
+    // Undo the division, rounding to the nearest BE 16 byte value
+    let original = (value * 1000.0).round() as u16;
+    can_extract::helper::insert_bytes(bytes, &original.to_be_bytes())
+}
+
+/// A message with signal metadata, so it keeps its CAN context when serialised
+#[derive(CanDecode, CanEncode, Debug)]
+#[can_extract(serialize)]
+struct OdometerStruct {
+    // Serialises as {"value": .., "unit": "km", "raw": ..} with the raw value in metres
+    // The decoder reads BE, so use_big_endian is set for the raw value to match
+    #[can_extract(
+        offset = 0,
+        extract = 2,
+        use_big_endian,
+        use_decoder = "divide_by_1000",
+        use_encoder = "multiply_by_1000",
+        unit = "km",
+        serialize_raw
+    )]
+    distance: f32,
+    // No metadata, so just {"value": ..}
+    #[can_extract(offset = 2)]
+    trips: u8,
+}
+
+/// The motohawk ExampleMessage used throughout the cantools docs, all Motorola signals
+/// BO_ 496 ExampleMessage: 8 PCM1
+///  SG_ Enable : 7|1@0- (1,0) [0|0] "-" Vector__XXX
+///  SG_ AverageRadius : 6|6@0+ (0.1,0) [0|5] "m" Vector__XXX
+///  SG_ Temperature : 0|12@0- (0.01,250) [229.53|270.47] "degK" Vector__XXX
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(arbitrary)]
+struct MotohawkStruct {
+    // start_bit and length follow DBC semantics - for use_big_endian the start bit is the MSB,
+    // and the signal runs down through the byte then on to bit 7 of the next byte
+    #[can_extract(start_bit = 7, length = 1, use_big_endian)]
+    enable: bool,
+    #[can_extract(start_bit = 6, length = 6, use_big_endian)]
+    average_radius: u8,
+    // Crosses from bit 0 of byte 0, through byte 1, into the top 3 bits of byte 2
+    #[can_extract(start_bit = 0, length = 12, use_big_endian)]
+    temperature: i16,
+}
+
+/// The same message as ARXML describes it, each start bit counting from the signal's LSB, with
+/// a little endian counter tacked on the end
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(byte_order = "big", bit_numbering = "lsb", dlc = 8, arbitrary)]
+struct MotohawkLsbStruct {
+    #[can_extract(start_bit = 7, length = 1)]
+    enable: bool,
+    // Members can still count from the MSB
+    #[can_extract(start_bit = 6, length = 6, bit_numbering = "msb")]
+    average_radius: u8,
+    #[can_extract(start_bit = 21, length = 12)]
+    temperature: i16,
+    #[can_extract(offset = 6, use_little_endian)]
+    counter: u16,
+}
+
+/// A float straddling byte boundaries, as DBC's SIG_VALTYPE_ 1
+/// SG_ Pressure : 4|32@1- (1,0) [0|0] "bar" Vector__XXX
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(arbitrary)]
+struct IntelFloatStruct {
+    #[can_extract(start_bit = 4, ieee_float, unit = "bar")]
+    pressure: f32,
+}
+
+/// The same float, Motorola this time
+/// SG_ Pressure : 3|32@0- (1,0) [0|0] "bar" Vector__XXX
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(arbitrary)]
+struct MotorolaFloatStruct {
+    #[can_extract(start_bit = 3, ieee_float, use_big_endian, unit = "bar")]
+    pressure: f32,
+}
+
+/// J1939 Electronic Engine Controller 1, sent by whichever engine is on the bus
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(pgn = 0xF004, name = "EEC1")]
+struct Eec1 {
+    #[can_extract(start_bit = 0, length = 4, spn = 899)]
+    torque_mode: u8,
+    #[can_extract(offset = 1, extract = 1, codec = Scaled<1, 1, { -125 }>, unit = "%", spn = 512)]
+    demand_torque: f32,
+    #[can_extract(offset = 2, extract = 1, codec = Scaled<1, 1, { -125 }>, unit = "%", spn = 513)]
+    actual_torque: f32,
+    #[can_extract(offset = 3, extract = 2, codec = Scaled<1, 8>, unit = "rpm", spn = 190)]
+    engine_speed: f32,
+    #[can_extract(offset = 5, spn = 1483)]
+    controlling_address: u8,
+}
+
+/// J1939 Engine Temperature 1, with each signal's DBC range and a different take on values outside it
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(pgn = 0xFEEE, name = "ET1", out_of_range = "flag", arbitrary)]
+struct Et1 {
+    // A coolant temperature past 210 is a sensor fault
+    #[can_extract(offset = 0, extract = 1, codec = Scaled<1, 1, { -40 }>, unit = "degC", spn = 110, min = -40, max = 210, out_of_range = "error")]
+    coolant: f32,
+    #[can_extract(offset = 1, extract = 1, codec = Scaled<1, 1, { -40 }>, unit = "degC", spn = 174, min = -40, max = 210, out_of_range = "clamp")]
+    fuel: f32,
+    #[can_extract(offset = 2, extract = 2, codec = Scaled<1, 32, { -273 }>, unit = "degC", spn = 175, min = -273, max = 1735, not_available = 0xFFFF)]
+    oil: Signal<f32>,
+}
+
+/// J1939 Cruise Control/Vehicle Speed, where any signal may be not available or in error
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(pgn = 0xFEF1, name = "CCVS1", arbitrary, serialize)]
+struct Ccvs {
+    #[can_extract(start_bit = 2, length = 2, spn = 70)]
+    parking_brake: Signal<u8>,
+    #[can_extract(offset = 1, extract = 2, codec = Scaled<1, 256>, unit = "km/h", spn = 84)]
+    speed: Signal<f32>,
+    #[can_extract(start_bit = 24, length = 2, spn = 595)]
+    cruise_active: Option<u8>,
+    // Not a J1939 signal, so its not available value comes from the DBC value table instead
+    #[can_extract(offset = 5, not_available = 0)]
+    target_gear: Signal<u8>,
+}
+
+/// A UDS data identifier record, read with ReadDataByIdentifier 0x0100
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(did = 0x0100)]
+struct BatteryStatus {
+    #[can_extract(offset = 0, extract = 2, use_big_endian, codec = Scaled<1, 1000>, unit = "V")]
+    voltage: f32,
+    #[can_extract(offset = 2, unit = "%")]
+    state_of_charge: u8,
+}
+
+/// The VIN record 0xF190, longer than a CAN frame so only read through CanDecodeSlice
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(did = 0xF190, dlc = 17)]
+struct VehicleIdentification {
+    #[can_extract(offset = 0)]
+    vin: [u8; 17],
+}
+
+/// OBD-II PID 0x42, declared here rather than in can_extract::obd2 like a manufacturer PID would be
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(pid = 0x42)]
+struct ControlModuleVoltage {
+    #[can_extract(offset = 0, extract = 2, use_big_endian, codec = Scaled<1, 1000>, unit = "V")]
+    voltage: f32,
+}
+
+/// TPDO1 of the motor controller on CANopen node 5, mapping the statusword and actual velocity
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(pdo = 1, node = 0x05)]
+struct MotorStatus {
+    #[can_extract(offset = 0)]
+    statusword: u16,
+    #[can_extract(offset = 2, unit = "rpm")]
+    velocity: i32,
+}
+
+/// RPDO1 of the same controller, the controlword and target velocity
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(pdo = 1, node = 0x05, rpdo)]
+struct MotorCommand {
+    #[can_extract(offset = 0)]
+    controlword: u16,
+    #[can_extract(offset = 2, unit = "rpm")]
+    target_velocity: i32,
+}
+
+/// An E2E profile 1 protected message
+/// The CRC lives in byte 0 and the alive counter in the low nibble of byte 1
+#[derive(CanDecode, Debug)]
+#[can_extract(e2e_profile = 1, e2e_data_id = 0x0123)]
+struct ProtectedStruct {
+    #[can_extract(offset = 2)]
+    speed: u16,
+}
+
+/// Profile 2 picks the data ID for each counter value out of a list, and tolerates a lost frame
+#[derive(CanDecode, Debug)]
+#[can_extract(
+    e2e_profile = 2,
+    e2e_data_id_list = "0x01,0x02,0x03,0x04,0x05,0x06,0x07,0x08,0x09,0x0A,0x0B,0x0C,0x0D,0x0E,0x0F,0x10",
+    e2e_max_delta_counter = 2
+)]
+struct Protected2Struct {
+    #[can_extract(offset = 2)]
+    speed: u16,
+}
+
+/// Tuple struct members are declared the same way, just without names
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(id = 0x701, view, arbitrary, serialize)]
+struct Heartbeat(
+    #[can_extract(offset = 0)] u8,
+    #[can_extract(offset = 1, use_big_endian)] u16,
+);
+
+/// Which makes for newtypes over a single signal
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(arbitrary)]
+struct BrakePressure(
+    #[can_extract(offset = 0, extract = 2, codec = Scaled<1, 10>, unit = "bar")] Signal<f32>,
+);
+
+/// Generic members are bound on what reading them takes - FieldBytes for whole bytes, RawBits
+/// for bit ranges and signals - rather than on the whole message
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(arbitrary)]
+struct Reading<T, S> {
+    #[can_extract(offset = 0)]
+    value: T,
+    #[can_extract(start_bit = 32, length = 4)]
+    status: S,
+    #[can_extract(offset = 5, unit = "%")]
+    quality: Signal<S>,
+}
+
+/// Lifetimes pass straight through, here for a member decoded to a borrowed label
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(arbitrary, serialize)]
+struct Labelled<'a, T> {
+    #[can_extract(offset = 0)]
+    value: T,
+    #[can_extract(
+        offset = 7,
+        extract = 1,
+        use_decoder = "mode_label",
+        use_encoder = "mode_index"
+    )]
+    mode: &'a str,
+}
+
+const MODES: [&str; 3] = ["Off", "Eco", "Sport"];
+
+fn mode_label(bytes: &[u8]) -> Result<&'static str, can_extract::Error> {
+    MODES
+        .get(bytes[0] as usize)
+        .copied()
+        .ok_or(can_extract::Error::ValueOutOfRange)
+}
+
+fn mode_index(mode: &&str, bytes: &mut [u8]) -> Result<(), can_extract::Error> {
+    let index = MODES.iter().position(|label| label == mode);
+    let index = index.ok_or(can_extract::Error::ValueOutOfRange)?;
+    can_extract::helper::insert_bytes(bytes, &[index as u8])
+}
+
+/// A trigger with no payload, decoding only when the DLC is right
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+#[can_extract(id = 0x080, dlc = 0)]
+struct Trigger;
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use can_extract::codec::{Bcd, Enumerated, ScaledSigned};
 
     use float_cmp::ApproxEq;
 
+    /// Members decoded through typed codecs, rather than decoder functions
+    #[derive(CanDecode, CanEncode, Debug, PartialEq)]
+    #[can_extract(arbitrary)]
+    struct CodecStruct {
+        // The same as TestStruct's c, but checked by the compiler and reported by CanLayout
+        #[can_extract(offset = 0, extract = 2, use_big_endian, codec = Scaled<1, 1000>)]
+        distance: f32,
+        // Two decimal digits, one per nibble
+        #[can_extract(offset = 2, extract = 1, codec = Bcd)]
+        hour: u8,
+        // A value table
+        #[can_extract(start_bit = 24, length = 2, codec = Enumerated<Gear>)]
+        gear: Gear,
+        // Codecs work on bit level members too - here 12 bits signed, in 0.5 steps from -40
+        #[can_extract(start_bit = 32, length = 12, codec = ScaledSigned<1, 2, { -40 }>)]
+        temperature: f32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Gear {
+        Park,
+        Reverse,
+        Neutral,
+        Drive,
+    }
+
+    impl TryFrom<u64> for Gear {
+        type Error = ();
+
+        fn try_from(raw: u64) -> Result<Self, Self::Error> {
+            match raw {
+                0 => Ok(Gear::Park),
+                1 => Ok(Gear::Reverse),
+                2 => Ok(Gear::Neutral),
+                3 => Ok(Gear::Drive),
+                _ => Err(()),
+            }
+        }
+    }
+
+    impl From<&Gear> for u64 {
+        fn from(gear: &Gear) -> u64 {
+            *gear as u64
+        }
+    }
+
     #[test]
     fn run_things() {
         let data: [u8; 8] = [5, 0, 0, 5, 0, 0, 0, 1];
@@ -56,4 +367,1741 @@ mod test {
         assert!(val.b == 5);
         assert!(val.c.approx_eq(0.001, (0.0, 2)));
     }
+
+    #[test]
+    fn round_trip() {
+        let val = TestStruct {
+            a: 5,
+            b: 5,
+            c: 0.001,
+        };
+        let data = can_extract::CanEncode::to_socketcan(&val).expect("Did not encode correctly");
+        assert_eq!(data, [5, 0, 0, 5, 0, 0, 0, 1]);
+
+        can_extract::assert_round_trip!(TestStruct);
+    }
+
+    #[test]
+    fn decode_slice() {
+        use can_extract::{CanDecodeSlice, Error};
+
+        // Declaring dlc = 8 means every frame must be exactly 8 bytes
+        assert_eq!((TestStruct::MIN_LEN, TestStruct::DLC), (8, Some(8)));
+        let val = TestStruct::from_slice(&[5, 0, 0, 5, 0, 0, 0, 1]).unwrap();
+        assert_eq!((val.a, val.b), (5, 5));
+        assert!(matches!(
+            TestStruct::from_slice(&[5, 0, 0, 5]),
+            Err(Error::FrameTooShort {
+                expected: 8,
+                actual: 4
+            })
+        ));
+        assert!(matches!(
+            TestStruct::from_slice(&[0; 9]),
+            Err(Error::DlcMismatch {
+                expected: 8,
+                actual: 9
+            })
+        ));
+
+        // Without one, a short frame decodes as long as it holds every member
+        // Temperature runs from byte 0 into byte 2
+        assert_eq!((MotohawkStruct::MIN_LEN, MotohawkStruct::DLC), (3, None));
+        assert_eq!(
+            MotohawkStruct::from_slice(&[0xC0, 0x06, 0xE0]).unwrap(),
+            MotohawkStruct {
+                enable: true,
+                average_radius: 32,
+                temperature: 55
+            }
+        );
+        let error = MotohawkStruct::from_slice(&[0xC0, 0x06]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Frame too short, expected at least 3 bytes but got 2"
+        );
+    }
+
+    #[test]
+    fn tuple_structs() {
+        use can_extract::{CanDecode, CanDecodeSlice, CanEncode, CanLayout, Error};
+
+        let frame = [7, 0x01, 0x02, 0, 0, 0, 0, 0];
+        let heartbeat = Heartbeat::from_socketcan(frame).unwrap();
+        assert_eq!(heartbeat, Heartbeat(7, 0x0102));
+        assert_eq!(heartbeat.to_socketcan().unwrap(), frame);
+        assert_eq!(Heartbeat::from_slice(&frame[..3]).unwrap(), heartbeat);
+
+        // Views and layouts go by index, as there are no names
+        let view = HeartbeatView::new(&frame);
+        assert_eq!(
+            (view.field_0().unwrap(), view.field_1().unwrap()),
+            (7, 0x0102)
+        );
+        let names = Heartbeat::SIGNALS.iter().map(|signal| signal.name);
+        assert_eq!(names.collect::<Vec<_>>(), ["0", "1"]);
+        assert_eq!(
+            serde_json::to_string(&heartbeat).unwrap(),
+            r#"[{"value":7},{"value":258}]"#
+        );
+        can_extract::assert_round_trip!(Heartbeat);
+
+        let pressure = BrakePressure::from_slice(&[0xE8, 0x03]).unwrap();
+        assert_eq!(pressure, BrakePressure(Signal::Valid(100.0)));
+        can_extract::assert_round_trip!(BrakePressure);
+
+        // A unit struct has nothing to decode, but still checks the DLC
+        assert_eq!(Trigger::from_slice(&[]).unwrap(), Trigger);
+        assert!(matches!(
+            Trigger::from_slice(&[0]),
+            Err(Error::DlcMismatch {
+                expected: 0,
+                actual: 1
+            })
+        ));
+        assert_eq!(Trigger.to_socketcan().unwrap(), [0; 8]);
+        assert!(Trigger::SIGNALS.is_empty());
+    }
+
+    #[test]
+    fn generics() {
+        use can_extract::{CanDecode, CanDecodeSlice, CanEncode};
+
+        let frame = [0x10, 0x27, 0, 0, 0x03, 0x5A, 0, 2];
+        let reading = Reading::<u32, u8>::from_socketcan(frame).unwrap();
+        assert_eq!(
+            reading,
+            Reading {
+                value: 10_000,
+                status: 3,
+                quality: Signal::Valid(90)
+            }
+        );
+        assert_eq!(
+            reading.to_socketcan().unwrap(),
+            [0x10, 0x27, 0, 0, 0x03, 0x5A, 0, 0]
+        );
+
+        // The same layout over other member types, where a wider quality takes up byte 6 too
+        assert_eq!(Reading::<i16, u16>::MIN_LEN, 7);
+        let reading = Reading::<i16, u16>::from_slice(&frame[..7]).unwrap();
+        assert_eq!((reading.value, reading.status), (10_000, 3));
+        assert_eq!(reading.quality, Signal::Valid(90));
+        can_extract::assert_round_trip!(Reading<f32, u8>);
+
+        let labelled = Labelled::<u16>::from_socketcan(frame).unwrap();
+        assert_eq!((labelled.value, labelled.mode), (10_000, "Sport"));
+        assert_eq!(
+            labelled.to_socketcan().unwrap(),
+            [0x10, 0x27, 0, 0, 0, 0, 0, 2]
+        );
+        assert_eq!(
+            serde_json::to_string(&labelled).unwrap(),
+            r#"{"value":{"value":10000},"mode":{"value":"Sport"}}"#
+        );
+        can_extract::assert_round_trip!(Labelled<'static, u16>);
+    }
+
+    /// A percentage, which rejects raw values over 100
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Percent(u8);
+
+    impl can_extract::CanField for Percent {
+        const BITS: u16 = 8;
+
+        fn decode(
+            frame: &[u8],
+            start_bit: u16,
+            length: u16,
+            byte_order: can_extract::ByteOrder,
+        ) -> Result<Self, can_extract::Error> {
+            match u8::decode(frame, start_bit, length, byte_order)? {
+                raw @ 0..=100 => Ok(Percent(raw)),
+                _ => Err(can_extract::Error::ValueOutOfRange),
+            }
+        }
+
+        fn encode(
+            &self,
+            frame: &mut [u8],
+            start_bit: u16,
+            length: u16,
+            byte_order: can_extract::ByteOrder,
+        ) -> Result<(), can_extract::Error> {
+            self.0.encode(frame, start_bit, length, byte_order)
+        }
+    }
+
+    /// A temperature sent as a byte offset by 40
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Celsius(i16);
+
+    impl can_extract::CanField for Celsius {
+        const BITS: u16 = 8;
+
+        fn decode(
+            frame: &[u8],
+            start_bit: u16,
+            length: u16,
+            byte_order: can_extract::ByteOrder,
+        ) -> Result<Self, can_extract::Error> {
+            let raw = u8::decode(frame, start_bit, length, byte_order)?;
+            Ok(Celsius(raw as i16 - 40))
+        }
+
+        fn encode(
+            &self,
+            frame: &mut [u8],
+            start_bit: u16,
+            length: u16,
+            byte_order: can_extract::ByteOrder,
+        ) -> Result<(), can_extract::Error> {
+            let raw = u8::try_from(self.0 + 40).map_err(|_| can_extract::Error::ValueOutOfRange)?;
+            raw.encode(frame, start_bit, length, byte_order)
+        }
+    }
+
+    /// Any type implementing CanField can be a member, arrays of them included
+    #[derive(CanDecode, CanEncode, Debug, PartialEq)]
+    #[can_extract(arbitrary)]
+    struct Climate {
+        #[can_extract(offset = 0, unit = "%")]
+        fan: Percent,
+        #[can_extract(offset = 1, unit = "degC")]
+        cabin: Celsius,
+        #[can_extract(offset = 2)]
+        vents: [Percent; 2],
+        #[can_extract(start_bit = 32, length = 1)]
+        air_conditioning: bool,
+        #[can_extract(start_bit = 33, length = 1)]
+        recirculate: bool,
+        /// A zone reads as None when its byte is one J1939 reserves, 0xFB to 0xFF
+        #[can_extract(offset = 5, use_big_endian)]
+        zones: [Option<u8>; 3],
+    }
+
+    /// Option members and Option array elements agree on which raw values are None
+    #[derive(CanDecode, CanEncode, Debug, PartialEq)]
+    #[can_extract(arbitrary)]
+    struct Doors {
+        #[can_extract(offset = 0)]
+        driver: Option<u8>,
+        #[can_extract(offset = 1)]
+        passengers: [Option<u8>; 3],
+    }
+
+    use can_extract::uom;
+
+    /// Units are part of the member types, so the frame's km/h can't be mistaken for m/s
+    #[derive(CanDecode, CanEncode, Debug, PartialEq)]
+    #[can_extract(arbitrary)]
+    struct WheelSpeed {
+        #[can_extract(offset = 0, extract = 2, factor = 0.01, unit = "kilometer_per_hour")]
+        front: uom::si::f32::Velocity,
+        // Any codec works on the quantity's f32, here to allow for negative slip
+        #[can_extract(offset = 2, extract = 2, codec = ScaledSigned<1, 100>, unit = "kilometer_per_hour")]
+        slip: uom::si::f32::Velocity,
+        #[can_extract(offset = 4, extract = 2, factor = 0.1, unit = "degree_celsius")]
+        brake_temperature: uom::si::f32::ThermodynamicTemperature,
+        // Without a quantity, factor is just shorthand for a Scaled codec
+        #[can_extract(offset = 6, extract = 1, factor = 0.5, unit = "%")]
+        pad_wear: f32,
+    }
+
+    #[test]
+    fn units_of_measure() {
+        use can_extract::{CanDecode, CanEncode, CanLayout, Scaling};
+        use uom::si::f32::Velocity;
+        use uom::si::thermodynamic_temperature::{degree_celsius, kelvin};
+        use uom::si::velocity::{kilometer_per_hour, meter_per_second};
+
+        let frame = [0x88, 0x13, 0x6A, 0xFF, 0xB5, 0x04, 0x28, 0];
+        let speed = WheelSpeed::from_socketcan(frame).unwrap();
+        assert_eq!(speed.front, Velocity::new::<kilometer_per_hour>(50.0));
+        assert!((speed.front.get::<meter_per_second>() - 13.888_889).abs() < 1e-4);
+        assert_eq!(speed.slip.get::<kilometer_per_hour>(), -1.5);
+        assert!((speed.brake_temperature.get::<kelvin>() - 393.65).abs() < 1e-3);
+        assert!((speed.brake_temperature.get::<degree_celsius>() - 120.5).abs() < 1e-3);
+        assert_eq!(speed.pad_wear, 20.0);
+        assert_eq!(speed.to_socketcan().unwrap(), frame);
+
+        // Quantities in other units go out in the frame's
+        let speed = WheelSpeed {
+            front: Velocity::new::<meter_per_second>(10.0),
+            ..speed
+        };
+        assert_eq!(&speed.to_socketcan().unwrap()[..2], &3600u16.to_le_bytes());
+
+        assert_eq!(
+            WheelSpeed::SIGNALS[0].scaling,
+            Some(Scaling {
+                factor: 0.01,
+                offset: 0.0
+            })
+        );
+        assert_eq!(WheelSpeed::SIGNALS[0].unit, Some("kilometer_per_hour"));
+        assert!(WheelSpeed::SIGNALS[1].signed);
+        can_extract::assert_round_trip!(WheelSpeed);
+    }
+
+    #[test]
+    fn physical_ranges() {
+        use can_extract::{CanDecode, CanEncode, CanLayout, CanValidate, Error};
+
+        let frame = [0x5A, 0xFF, 0x20, 0x26, 0xFF, 0xFF, 0xFF, 0xFF];
+        let et1 = Et1::from_socketcan(frame).unwrap();
+        assert_eq!(
+            et1,
+            Et1 {
+                coolant: 50.0,
+                fuel: 210.0,
+                oil: Signal::Valid(32.0),
+            }
+        );
+        assert_eq!(et1.out_of_range(), 0);
+        assert_eq!(et1.to_socketcan().unwrap()[1], 0xFA);
+
+        // Errors name the signal and the value it decoded as
+        let mut coolant_fault = frame;
+        coolant_fault[0] = 0xFF;
+        assert!(matches!(
+            Et1::from_socketcan(coolant_fault),
+            Err(Error::PhysicalOutOfRange {
+                signal: "coolant",
+                value
+            }) if value == 215.0
+        ));
+
+        // Flagged values come through as sent
+        let mut oil_fault = frame;
+        oil_fault[2..4].copy_from_slice(&[0x00, 0xFE]);
+        let et1 = Et1::from_socketcan(oil_fault).unwrap();
+        assert_eq!(et1.oil, Signal::Valid(1759.0));
+        assert_eq!(et1.out_of_range(), 0b100);
+
+        assert_eq!(
+            Et1::SIGNALS
+                .iter()
+                .map(|signal| (signal.min, signal.max))
+                .collect::<Vec<_>>(),
+            [
+                (Some(-40.0), Some(210.0)),
+                (Some(-40.0), Some(210.0)),
+                (Some(-273.0), Some(1735.0))
+            ]
+        );
+        can_extract::assert_round_trip!(Et1);
+    }
+
+    #[test]
+    fn can_fields() {
+        use can_extract::{ByteOrder, CanDecode, CanEncode, CanField, CanLayout, Error};
+
+        let frame = [40, 62, 100, 0, 0b10, 21, 0xFF, 19];
+        let climate = Climate::from_socketcan(frame).unwrap();
+        assert_eq!(
+            climate,
+            Climate {
+                fan: Percent(40),
+                cabin: Celsius(22),
+                vents: [Percent(100), Percent(0)],
+                air_conditioning: false,
+                recirculate: true,
+                zones: [Some(21), None, Some(19)],
+            }
+        );
+        assert_eq!(climate.to_socketcan().unwrap(), frame);
+        assert_eq!(Climate::SIGNALS[2].length, 16);
+        can_extract::assert_round_trip!(Climate);
+
+        // The member types' own checks still apply
+        let mut frame = frame;
+        frame[0] = 101;
+        assert!(matches!(
+            Climate::from_socketcan(frame),
+            Err(Error::ValueOutOfRange)
+        ));
+
+        // Error and reserved raw values are None too, whether the Option is a member or not
+        let frame = [0xFE, 0xFE, 0xFB, 0x05, 0, 0, 0, 0];
+        let doors = Doors::from_socketcan(frame).unwrap();
+        assert_eq!(
+            doors,
+            Doors {
+                driver: None,
+                passengers: [None, None, Some(5)],
+            }
+        );
+        let frame = [0xFB, 0xFF, 0x05, 0xFE, 0, 0, 0, 0];
+        let doors = Doors::from_socketcan(frame).unwrap();
+        assert_eq!(doors.driver, None);
+        assert_eq!(doors.passengers, [None, Some(5), None]);
+        // Values that would read back as None can't be sent either way
+        let doors = Doors {
+            driver: Some(0xFE),
+            passengers: [None; 3],
+        };
+        assert!(matches!(doors.to_socketcan(), Err(Error::ValueOutOfRange)));
+        let doors = Doors {
+            driver: None,
+            passengers: [Some(0xFE), None, None],
+        };
+        assert!(matches!(doors.to_socketcan(), Err(Error::ValueOutOfRange)));
+        can_extract::assert_round_trip!(Doors);
+
+        // 128 bit integers are read in two halves, signed ones sign extended from their length
+        let frame: Vec<u8> = (1..=16).collect();
+        let raw = u128::from_be_bytes(frame.clone().try_into().unwrap());
+        assert_eq!(
+            u128::decode(&frame, 7, 128, ByteOrder::BigEndian).unwrap(),
+            raw
+        );
+        assert_eq!(
+            u128::decode(&frame, 0, 128, ByteOrder::LittleEndian).unwrap(),
+            raw.swap_bytes()
+        );
+        assert_eq!(
+            i128::decode(&[0xFF; 9], 0, 72, ByteOrder::LittleEndian).unwrap(),
+            -1
+        );
+        let mut encoded = vec![0; 16];
+        raw.encode(&mut encoded, 7, 128, ByteOrder::BigEndian)
+            .unwrap();
+        assert_eq!(encoded, frame);
+    }
+
+    #[test]
+    fn serialize_signals() {
+        let data: [u8; 8] = [0x30, 0x39, 3, 0, 0, 0, 0, 0];
+        let val: OdometerStruct =
+            can_extract::CanDecode::from_socketcan(data).expect("Did not decode correctly");
+        assert_eq!(
+            serde_json::to_string(&val).unwrap(),
+            r#"{"distance":{"value":12.345,"unit":"km","raw":12345},"trips":{"value":3}}"#
+        );
+    }
+
+    #[test]
+    fn motorola_bits() {
+        // Enable = 1, AverageRadius = 3.2, Temperature = 250.55
+        let data: [u8; 8] = [0xC0, 0x06, 0xE0, 0, 0, 0, 0, 0];
+        let val: MotohawkStruct =
+            can_extract::CanDecode::from_socketcan(data).expect("Did not decode correctly");
+        assert_eq!(
+            val,
+            MotohawkStruct {
+                enable: true,
+                average_radius: 32,
+                temperature: 55
+            }
+        );
+
+        // Temperature = 249.45 is negative raw, so needs sign extending out of 12 bits
+        let val = MotohawkStruct {
+            temperature: -55,
+            ..val
+        };
+        let data = can_extract::CanEncode::to_socketcan(&val).expect("Did not encode correctly");
+        assert_eq!(data, [0xC1, 0xF9, 0x20, 0, 0, 0, 0, 0]);
+        assert_eq!(can_extract::CanDecode::from_socketcan(data).ok(), Some(val));
+
+        can_extract::assert_round_trip!(MotohawkStruct);
+
+        // Struct wide byte order and bit numbering give the same layout
+        let data = [0xC1, 0xF9, 0x20, 0, 0, 0, 0x34, 0x12];
+        let lsb: MotohawkLsbStruct = can_extract::CanDecode::from_socketcan(data).unwrap();
+        assert_eq!(
+            lsb,
+            MotohawkLsbStruct {
+                enable: true,
+                average_radius: 32,
+                temperature: -55,
+                counter: 0x1234,
+            }
+        );
+        assert_eq!(can_extract::CanEncode::to_socketcan(&lsb).unwrap(), data);
+        use can_extract::CanLayout;
+        assert_eq!(
+            MotohawkLsbStruct::SIGNALS
+                .iter()
+                .map(|signal| signal.start_bit)
+                .collect::<Vec<_>>(),
+            [7, 6, 0, 48]
+        );
+        can_extract::assert_round_trip!(MotohawkLsbStruct);
+    }
+
+    #[test]
+    fn ieee_floats() {
+        use can_extract::CanLayout;
+
+        // 1.5 is 0x3FC00000
+        let intel: [u8; 8] = [0x00, 0x00, 0x00, 0xFC, 0x03, 0, 0, 0];
+        let val: IntelFloatStruct =
+            can_extract::CanDecode::from_socketcan(intel).expect("Did not decode correctly");
+        assert_eq!(val.pressure, 1.5);
+        assert_eq!(can_extract::CanEncode::to_socketcan(&val).unwrap(), intel);
+
+        let motorola: [u8; 8] = [0x03, 0xFC, 0, 0, 0, 0, 0, 0];
+        let val: MotorolaFloatStruct =
+            can_extract::CanDecode::from_socketcan(motorola).expect("Did not decode correctly");
+        assert_eq!(val.pressure, 1.5);
+        assert_eq!(
+            can_extract::CanEncode::to_socketcan(&val).unwrap(),
+            motorola
+        );
+
+        assert_eq!(IntelFloatStruct::SIGNALS[0].length, 32);
+        assert_eq!(MotorolaFloatStruct::SIGNALS[0].start_bit, 3);
+
+        can_extract::assert_round_trip!(IntelFloatStruct);
+        can_extract::assert_round_trip!(MotorolaFloatStruct);
+    }
+
+    #[test]
+    fn j1939() {
+        use can_extract::j1939::{Id, J1939Message};
+        use can_extract::CanLayout;
+
+        // EEC1 from the engine at address 0x00
+        let id = Id::from_raw(0x0CF0_0400);
+        assert_eq!(
+            id,
+            Id {
+                priority: 3,
+                pgn: 0xF004,
+                source: 0x00,
+                destination: None
+            }
+        );
+        assert_eq!(id.to_raw(), 0x0CF0_0400);
+
+        // A PDU1 request from address 0xF9 to 0x00 - the destination isn't part of the PGN
+        let id = Id::from_raw(0x18EA_00F9);
+        assert_eq!(
+            id,
+            Id {
+                priority: 6,
+                pgn: 0xEA00,
+                source: 0xF9,
+                destination: Some(0x00)
+            }
+        );
+        assert_eq!(id.to_raw(), 0x18EA_00F9);
+
+        // 1500 rpm, 15% demanded and 25% actual torque
+        let data: [u8; 8] = [0x03, 0x8C, 0x96, 0xE0, 0x2E, 0x00, 0, 0];
+        let val = Eec1::from_j1939(0x0CF0_0400, data)
+            .expect("Did not decode correctly")
+            .expect("Did not match the PGN");
+        assert_eq!(
+            val,
+            Eec1 {
+                torque_mode: 3,
+                demand_torque: 15.0,
+                actual_torque: 25.0,
+                engine_speed: 1500.0,
+                controlling_address: 0,
+            }
+        );
+        assert_eq!(can_extract::CanEncode::to_socketcan(&val).unwrap(), data);
+
+        // The PGN matches from any source address, but nothing else does
+        assert!(Eec1::matches(0x0CF0_0417));
+        assert!(Eec1::from_j1939(0x0CF0_0300, data).unwrap().is_none());
+
+        assert_eq!(Eec1::PGN, 0xF004);
+        assert_eq!(Eec1::ID, None);
+        assert_eq!(Eec1::NAME, "EEC1");
+        assert_eq!(Eec1::SIGNALS[3].spn, Some(190));
+        assert_eq!(Eec1::SIGNALS[3].unit, Some("rpm"));
+    }
+
+    #[test]
+    fn j1939_signals() {
+        // Parking brake set, 25 km/h, cruise active, gear 3
+        let data: [u8; 8] = [0x04, 0x00, 0x19, 0x01, 0, 0x03, 0, 0];
+        let val: Ccvs =
+            can_extract::CanDecode::from_socketcan(data).expect("Did not decode correctly");
+        assert_eq!(
+            val,
+            Ccvs {
+                parking_brake: Signal::Valid(1),
+                speed: Signal::Valid(25.0),
+                cruise_active: Some(1),
+                target_gear: Signal::Valid(3),
+            }
+        );
+        assert_eq!(can_extract::CanEncode::to_socketcan(&val).unwrap(), data);
+
+        // Everything not available
+        let data: [u8; 8] = [0x0C, 0xFF, 0xFF, 0x03, 0, 0x00, 0, 0];
+        let val: Ccvs =
+            can_extract::CanDecode::from_socketcan(data).expect("Did not decode correctly");
+        assert_eq!(
+            val,
+            Ccvs {
+                parking_brake: Signal::NotAvailable,
+                speed: Signal::NotAvailable,
+                cruise_active: None,
+                target_gear: Signal::NotAvailable,
+            }
+        );
+        assert_eq!(can_extract::CanEncode::to_socketcan(&val).unwrap(), data);
+
+        // Error indicators, and the reserved range of a 16 bit signal
+        let data: [u8; 8] = [0x08, 0x00, 0xFE, 0x02, 0, 0x01, 0, 0];
+        let val: Ccvs =
+            can_extract::CanDecode::from_socketcan(data).expect("Did not decode correctly");
+        assert_eq!(val.parking_brake, Signal::Error);
+        assert_eq!(val.speed, Signal::Error);
+        assert_eq!(val.cruise_active, None);
+        let data: [u8; 8] = [0, 0x00, 0xFC, 0, 0, 0, 0, 0];
+        let val: Ccvs =
+            can_extract::CanDecode::from_socketcan(data).expect("Did not decode correctly");
+        assert_eq!(val.speed, Signal::Reserved);
+
+        // 255.5 km/h would be sent as 0xFF80, which reads back as not available
+        let val = Ccvs {
+            speed: Signal::Valid(255.5),
+            ..val
+        };
+        assert!(matches!(
+            can_extract::CanEncode::to_socketcan(&val),
+            Err(can_extract::Error::ValueOutOfRange)
+        ));
+
+        assert_eq!(
+            serde_json::to_string(&Ccvs {
+                parking_brake: Signal::Error,
+                speed: Signal::Valid(25.0),
+                cruise_active: None,
+                target_gear: Signal::NotAvailable,
+            })
+            .unwrap(),
+            r#"{"parking_brake":{"value":"Error"},"speed":{"value":25.0,"unit":"km/h"},"cruise_active":{"value":null},"target_gear":{"value":"NotAvailable"}}"#
+        );
+
+        can_extract::assert_round_trip!(Ccvs);
+    }
+
+    /// VIN, terminated by a '*'
+    #[derive(Debug, PartialEq)]
+    struct Vin(String);
+
+    impl can_extract::CanDecodeSlice for Vin {
+        fn from_slice(payload: &[u8]) -> Result<Self, can_extract::Error> {
+            let vin = payload.split(|&b| b == b'*').next().unwrap_or_default();
+            String::from_utf8(vin.to_vec())
+                .map(Vin)
+                .map_err(|_| can_extract::Error::InvalidBytesConversion)
+        }
+    }
+
+    #[test]
+    fn j1939_transport() {
+        use can_extract::j1939::tp::{AbortReason, Event, Payload, Reassembler};
+        use std::time::Duration;
+
+        let ms = Duration::from_millis;
+        let vin = b"1FUJGLDR0CLBP8834*\xFF\xFF\xFF";
+
+        // BAM from the engine, 18 bytes over 3 packets
+        let mut tp = Reassembler::new(Some(0xF9));
+        let events = tp
+            .push(
+                ms(0),
+                0x1CEC_FF00,
+                &[0x20, 18, 0, 3, 0xFF, 0xEC, 0xFE, 0x00],
+            )
+            .unwrap();
+        assert!(events.is_empty());
+        for (i, packet) in vin.chunks(7).enumerate() {
+            let mut frame = [i as u8 + 1; 8];
+            frame[1..].copy_from_slice(packet);
+            let events = tp.push(ms(50 * i as u64), 0x1CEB_FF00, &frame).unwrap();
+            if i < 2 {
+                assert!(events.is_empty());
+            } else {
+                let [Event::Complete(payload)] = &events[..] else {
+                    panic!("Expected a complete payload, got {:?}", events);
+                };
+                assert_eq!(payload.pgn, 0xFEEC);
+                assert_eq!(payload.source, 0x00);
+                assert_eq!(payload.destination, None);
+                assert_eq!(payload.decode::<Vin>().unwrap().0, "1FUJGLDR0CLBP8834");
+            }
+        }
+
+        // RTS to us, 2 packets per CTS
+        let events = tp
+            .push(
+                ms(1000),
+                0x1CEC_F900,
+                &[0x10, 18, 0, 3, 2, 0xEC, 0xFE, 0x00],
+            )
+            .unwrap();
+        assert_eq!(
+            events,
+            [Event::Send {
+                id: 0x1CEC_00F9,
+                data: [0x11, 2, 1, 0xFF, 0xFF, 0xEC, 0xFE, 0x00]
+            }]
+        );
+        let mut events = Vec::new();
+        for (i, packet) in vin.chunks(7).enumerate() {
+            let mut frame = [i as u8 + 1; 8];
+            frame[1..].copy_from_slice(packet);
+            events.extend(tp.push(ms(1100), 0x1CEB_F900, &frame).unwrap());
+        }
+        assert_eq!(
+            events,
+            [
+                Event::Send {
+                    id: 0x1CEC_00F9,
+                    data: [0x11, 1, 3, 0xFF, 0xFF, 0xEC, 0xFE, 0x00]
+                },
+                Event::Send {
+                    id: 0x1CEC_00F9,
+                    data: [0x13, 18, 0, 3, 0xFF, 0xEC, 0xFE, 0x00]
+                },
+                Event::Complete(Payload {
+                    pgn: 0xFEEC,
+                    source: 0x00,
+                    destination: Some(0xF9),
+                    data: vin[..18].to_vec(),
+                }),
+            ]
+        );
+
+        // A BAM that stops part way through times out
+        tp.push(
+            ms(2000),
+            0x1CEC_FF00,
+            &[0x20, 18, 0, 3, 0xFF, 0xEC, 0xFE, 0x00],
+        )
+        .unwrap();
+        tp.push(ms(2050), 0x1CEB_FF00, &[1, 0, 0, 0, 0, 0, 0, 0])
+            .unwrap();
+        assert!(tp.poll(ms(2500)).is_empty());
+        assert_eq!(
+            tp.poll(ms(3000)),
+            [Event::Aborted {
+                pgn: 0xFEEC,
+                source: 0x00,
+                destination: None,
+                reason: AbortReason::Timeout
+            }]
+        );
+
+        // And so does one the sender aborts, or that skips a packet
+        tp.push(
+            ms(4000),
+            0x1CEC_F900,
+            &[0x10, 18, 0, 3, 0xFF, 0xEC, 0xFE, 0x00],
+        )
+        .unwrap();
+        let events = tp
+            .push(
+                ms(4010),
+                0x1CEC_F900,
+                &[0xFF, 2, 0xFF, 0xFF, 0xFF, 0xEC, 0xFE, 0x00],
+            )
+            .unwrap();
+        assert!(matches!(
+            events[..],
+            [Event::Aborted {
+                reason: AbortReason::Resources,
+                ..
+            }]
+        ));
+        tp.push(
+            ms(5000),
+            0x1CEC_FF00,
+            &[0x20, 18, 0, 3, 0xFF, 0xEC, 0xFE, 0x00],
+        )
+        .unwrap();
+        let events = tp
+            .push(ms(5010), 0x1CEB_FF00, &[2, 0, 0, 0, 0, 0, 0, 0])
+            .unwrap();
+        assert!(matches!(
+            events[..],
+            [Event::Aborted {
+                reason: AbortReason::BadSequence,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn j1939_dm1() {
+        use can_extract::j1939::dm1::{Dtc, Flash, Lamp, Lamps, RawDtc};
+        use can_extract::j1939::tp::Payload;
+        use can_extract::j1939::{Dm1, J1939Message};
+        use can_extract::{CanDecodeSlice, CanLayout, Error};
+
+        // Malfunction and amber lamps on, flash not available, SPN 190 FMI 2 seen 5 times
+        let data: [u8; 8] = [0x44, 0xFF, 0xBE, 0x00, 0x02, 0x05, 0xFF, 0xFF];
+        let dm1 = Dm1::from_j1939(0x18FE_CA00, data)
+            .expect("Did not decode correctly")
+            .expect("Did not match the PGN");
+        let engine_speed = Dtc {
+            spn: 190,
+            fmi: 2,
+            occurrence_count: 5,
+            conversion_method: false,
+        };
+        assert_eq!(
+            dm1,
+            Dm1 {
+                lamps: Lamps {
+                    malfunction: Signal::Valid(Lamp::On),
+                    red_stop: Signal::Valid(Lamp::Off),
+                    amber_warning: Signal::Valid(Lamp::On),
+                    protect: Signal::Valid(Lamp::Off),
+                },
+                flash: Lamps {
+                    malfunction: Signal::NotAvailable,
+                    red_stop: Signal::NotAvailable,
+                    amber_warning: Signal::NotAvailable,
+                    protect: Signal::NotAvailable,
+                },
+                dtcs: vec![engine_speed],
+            }
+        );
+        assert_eq!(dm1.to_payload().unwrap(), data);
+
+        // No faults is an all zero DTC
+        let data: [u8; 8] = [0x00, 0xFF, 0, 0, 0, 0, 0xFF, 0xFF];
+        let quiet: Dm1 = can_extract::CanDecode::from_socketcan(data).unwrap();
+        assert!(quiet.dtcs.is_empty());
+        assert_eq!(quiet.to_payload().unwrap(), data);
+
+        // Two DTCs need the transport protocol, the second with a 19 bit SPN
+        let payload = Payload {
+            pgn: Dm1::PGN,
+            source: 0x00,
+            destination: None,
+            data: vec![0x44, 0xFF, 0xBE, 0x00, 0x02, 0x05, 0x08, 0xF0, 0xFF, 0x81],
+        };
+        let dm1: Dm1 = payload.decode().expect("Did not decode correctly");
+        assert_eq!(
+            dm1.dtcs,
+            [
+                engine_speed,
+                Dtc {
+                    spn: 0x7_F008,
+                    fmi: 31,
+                    occurrence_count: 1,
+                    conversion_method: true,
+                }
+            ]
+        );
+        assert_eq!(dm1.to_payload().unwrap(), payload.data);
+        assert_eq!(dm1.flash.protect, Signal::<Flash>::NotAvailable);
+
+        // The lamp and DTC layouts are derived, so they're described like any other message
+        assert_eq!(
+            RawDtc::SIGNALS
+                .iter()
+                .map(|signal| (signal.name, signal.start_bit, signal.length))
+                .collect::<Vec<_>>(),
+            [
+                ("spn_low", 0, 16),
+                ("fmi", 16, 5),
+                ("spn_high", 21, 3),
+                ("occurrence_count", 24, 7),
+                ("conversion_method", 31, 1)
+            ]
+        );
+        assert_eq!(Lamps::<Lamp>::SIGNALS[0].start_bit, 6);
+        can_extract::assert_round_trip!(RawDtc);
+
+        // J1939-73 reserves a raw 2, and there's no error value to send
+        let data: [u8; 8] = [0x9B, 0xFF, 0, 0, 0, 0, 0xFF, 0xFF];
+        let dm1: Dm1 = can_extract::CanDecode::from_socketcan(data).unwrap();
+        assert_eq!(
+            dm1.lamps,
+            Lamps {
+                malfunction: Signal::Reserved,
+                red_stop: Signal::Valid(Lamp::On),
+                amber_warning: Signal::Reserved,
+                protect: Signal::NotAvailable,
+            }
+        );
+        assert_eq!(dm1.to_payload().unwrap(), data);
+        let lamps = Lamps {
+            malfunction: Signal::Error,
+            ..dm1.lamps
+        };
+        assert!(matches!(
+            can_extract::CanEncode::to_socketcan(&lamps),
+            Err(Error::ValueOutOfRange)
+        ));
+
+        assert!(matches!(
+            Dm1::from_slice(&[0x44]),
+            Err(Error::FrameTooShort {
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn nmea2000() {
+        use can_extract::j1939::{Id, J1939Message};
+        use can_extract::nmea2000::{
+            fast_packet_frames, EngineParametersDynamic, PositionRapidUpdate, Reassembler,
+            VesselHeading, PGN_ENGINE_PARAMETERS_DYNAMIC, TIMEOUT,
+        };
+        use can_extract::CanDecode;
+        use std::time::Duration;
+
+        let id = |pgn, source| {
+            Id {
+                priority: 2,
+                pgn,
+                source,
+                destination: None,
+            }
+            .to_raw()
+        };
+
+        // Single frame PGNs decode straight from the frame
+        let frame = [0x07, 0x7B, 0x3D, 0x00, 0x00, 0xFF, 0x7F, 0xFD];
+        let heading = VesselHeading::from_j1939(id(127250, 0x10), frame)
+            .unwrap()
+            .expect("Did not match the PGN");
+        assert_eq!(heading.sid, 7);
+        assert!(matches!(heading.heading, Signal::Valid(h) if h.approx_eq(1.5739, (0.0001, 2))));
+        assert_eq!(heading.variation, Signal::NotAvailable);
+        assert_eq!(heading.reference, Signal::Valid(1));
+
+        let frame = [0x80, 0xA5, 0x2C, 0x1A, 0xFF, 0xFF, 0xFF, 0x7F];
+        let position = PositionRapidUpdate::from_socketcan(frame).unwrap();
+        assert!(
+            matches!(position.latitude, Signal::Valid(l) if l.approx_eq(43.913_356_8, (1e-9, 2)))
+        );
+        assert_eq!(position.longitude, Signal::NotAvailable);
+
+        // 26 bytes, so a first frame and 3 more
+        let mut payload = vec![0u8; 26];
+        payload[0] = 1;
+        payload[1..3].copy_from_slice(&450u16.to_le_bytes());
+        payload[5..7].copy_from_slice(&35_315u16.to_le_bytes());
+        payload[7..9].copy_from_slice(&1_420i16.to_le_bytes());
+        payload[11..15].copy_from_slice(&3_600u32.to_le_bytes());
+        payload[24] = 0x7F;
+        payload[25] = (-5i8) as u8;
+        let frames = fast_packet_frames(2, &payload).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0][..2], [0x40, 26]);
+        assert_eq!(frames[3][0], 0x43);
+
+        let mut reassembler = Reassembler::new([]);
+        reassembler.register::<EngineParametersDynamic>();
+        let now = Duration::from_secs(1);
+        let engine = id(PGN_ENGINE_PARAMETERS_DYNAMIC, 0x23);
+        // Another engine's fast packet interleaved with the first
+        let other = fast_packet_frames(0, &[0xAA; 20]).unwrap();
+        let other_engine = id(PGN_ENGINE_PARAMETERS_DYNAMIC, 0x24);
+        let mut complete = Vec::new();
+        for (frame, other) in frames.iter().zip(&other) {
+            complete.extend(reassembler.push(now, engine, frame).unwrap());
+            complete.extend(reassembler.push(now, other_engine, other).unwrap());
+        }
+        complete.extend(reassembler.push(now, engine, &frames[3]).unwrap());
+        assert_eq!(complete.len(), 2);
+        assert_eq!(complete[0].source, 0x24);
+        assert_eq!(complete[0].data, [0xAA; 20]);
+        assert_eq!(complete[1].source, 0x23);
+        assert_eq!(complete[1].data, payload);
+
+        let dynamic = complete[1].decode::<EngineParametersDynamic>().unwrap();
+        assert_eq!(dynamic.instance, 1);
+        assert!(matches!(dynamic.oil_pressure, Signal::Valid(p) if p.approx_eq(45.0, (0.0, 2))));
+        assert!(
+            matches!(dynamic.coolant_temperature, Signal::Valid(t) if t.approx_eq(353.15, (0.001, 2)))
+        );
+        assert!(
+            matches!(dynamic.alternator_potential, Signal::Valid(v) if v.approx_eq(14.2, (0.001, 2)))
+        );
+        assert_eq!(dynamic.total_engine_hours, Signal::Valid(3600));
+        assert_eq!(dynamic.load, Signal::NotAvailable);
+        assert_eq!(dynamic.torque, Signal::Valid(-5));
+
+        // A lost frame drops the message
+        for frame in [frames[0], frames[2], frames[3]] {
+            assert_eq!(reassembler.push(now, engine, &frame).unwrap(), None);
+        }
+        // And so does a stall
+        reassembler.push(now, engine, &frames[0]).unwrap();
+        let later = now + TIMEOUT + Duration::from_millis(1);
+        for frame in &frames[1..] {
+            assert_eq!(reassembler.push(later, engine, frame).unwrap(), None);
+        }
+
+        // Anything not registered comes straight back
+        let frame = [0u8; 8];
+        let payload = reassembler
+            .push(now, id(127488, 0x23), &frame)
+            .unwrap()
+            .unwrap();
+        assert_eq!(payload.pgn, 127488);
+        assert_eq!(payload.data, frame);
+    }
+
+    #[test]
+    fn iso_tp() {
+        use can_extract::isotp::{Config, Receiver, Sender};
+        use can_extract::CanDecodeSlice;
+        use std::collections::VecDeque;
+        use std::time::Duration;
+
+        let ms = Duration::from_millis;
+        let config = Config {
+            block_size: 2,
+            st_min: ms(10),
+            ..Config::default()
+        };
+        let mut sender = Sender::new(config);
+        let mut receiver = Receiver::new(config);
+        let mut to_receiver = VecDeque::new();
+        let mut to_sender = VecDeque::new();
+
+        // A single frame is done in one go
+        sender.send(ms(0), b"\x3E\x00", &mut to_receiver).unwrap();
+        assert_eq!(
+            to_receiver,
+            [[0x02, 0x3E, 0x00, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC]]
+        );
+        let payload = receiver.receive(ms(0), &mut to_receiver, &mut to_sender);
+        assert_eq!(payload.unwrap().as_deref(), Some(&b"\x3E\x00"[..]));
+
+        // 27 bytes needs a first frame, then 2 blocks of consecutive frames 10ms apart
+        let vin = b"1FUJGLDR0CLBP8834*\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+        sender.send(ms(0), vin, &mut to_receiver).unwrap();
+        assert_eq!(
+            to_receiver,
+            [[0x10, 0x1B, b'1', b'F', b'U', b'J', b'G', b'L']]
+        );
+        let mut wire = Vec::new();
+        let mut payload = None;
+        for now in (0..100).step_by(5).map(ms) {
+            wire.extend(to_receiver.iter().copied());
+            if let Some(complete) = receiver
+                .receive(now, &mut to_receiver, &mut to_sender)
+                .unwrap()
+            {
+                payload = Some(complete);
+            }
+            wire.extend(to_sender.iter().copied());
+            sender.pump(now, &mut to_sender, &mut to_receiver).unwrap();
+        }
+        assert!(sender.is_idle());
+        assert_eq!(
+            wire,
+            [
+                [0x10, 0x1B, b'1', b'F', b'U', b'J', b'G', b'L'],
+                [0x30, 0x02, 0x0A, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC],
+                [0x21, b'D', b'R', b'0', b'C', b'L', b'B', b'P'],
+                [0x22, b'8', b'8', b'3', b'4', b'*', 0x00, 0x00],
+                [0x30, 0x02, 0x0A, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC],
+                [0x23, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+            ]
+        );
+        let payload = payload.expect("Did not reassemble the payload");
+        assert_eq!(payload, vin);
+        assert_eq!(Vin::from_slice(&payload).unwrap().0, "1FUJGLDR0CLBP8834");
+
+        // A receiver that can't take the payload refuses it
+        let mut small = Receiver::new(Config {
+            max_len: 16,
+            ..config
+        });
+        sender.send(ms(200), vin, &mut to_receiver).unwrap();
+        assert!(matches!(
+            small.receive(ms(200), &mut to_receiver, &mut to_sender),
+            Err(can_extract::Error::TransportOverflow)
+        ));
+        assert!(matches!(
+            sender.pump(ms(200), &mut to_sender, &mut to_receiver),
+            Err(can_extract::Error::TransportOverflow)
+        ));
+
+        // And a sender that never hears back gives up
+        sender.send(ms(300), vin, &mut to_receiver).unwrap();
+        assert!(sender.poll(ms(1000), &mut to_receiver).is_ok());
+        assert!(matches!(
+            sender.poll(ms(1400), &mut to_receiver),
+            Err(can_extract::Error::TransportTimeout)
+        ));
+    }
+
+    #[test]
+    fn canopen() {
+        use can_extract::canopen::{
+            CobId, Event, Function, Initiate, Pdo, Record, Sdo, SdoMonitor, Transfer,
+        };
+        use can_extract::CanLayout;
+
+        assert_eq!(MotorStatus::cob_id(), 0x185);
+        assert_eq!(MotorCommand::cob_id(), 0x205);
+        assert_eq!(MotorStatus::ID, Some(0x185));
+        assert_eq!(
+            CobId::from_raw(0x185),
+            CobId {
+                function: Function::Tpdo(1),
+                node: 5
+            }
+        );
+        assert_eq!(CobId::from_raw(0x47F).function, Function::Rpdo(3));
+        assert_eq!(CobId::from_raw(0x080).function, Function::Sync);
+        assert_eq!(CobId::from_raw(0x085).function, Function::Emergency);
+        assert_eq!(CobId::from_raw(0x705).function, Function::Heartbeat);
+        for raw in [0x000, 0x080, 0x181, 0x27F, 0x4FF, 0x581, 0x67F, 0x705] {
+            assert_eq!(CobId::from_raw(raw).to_raw(), raw);
+        }
+
+        let frame = [0x37, 0x06, 0xE8, 0x03, 0x00, 0x00, 0x00, 0x00];
+        let status = MotorStatus::from_canopen(0x185, frame)
+            .unwrap()
+            .expect("Did not match the COB-ID");
+        assert_eq!(status.statusword, 0x0637);
+        assert_eq!(status.velocity, 1000);
+        assert_eq!(MotorStatus::from_canopen(0x186, frame).unwrap(), None);
+        assert_eq!(MotorCommand::from_canopen(0x185, frame).unwrap(), None);
+
+        // Expedited download of 0x6040:00 (the controlword), confirmed by the server
+        let mut monitor = SdoMonitor::new();
+        let request = [0x2B, 0x40, 0x60, 0x00, 0x0F, 0x00, 0x00, 0x00];
+        assert_eq!(
+            Sdo::from_request(request).unwrap(),
+            Sdo::InitiateDownload {
+                index: 0x6040,
+                subindex: 0,
+                data: Initiate::Expedited(vec![0x0F, 0x00])
+            }
+        );
+        assert_eq!(
+            Sdo::from_request(request).unwrap().to_frame().unwrap(),
+            request
+        );
+        assert_eq!(monitor.push(0x605, &request).unwrap(), None);
+        let response = [0x60, 0x40, 0x60, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let Some(Event::Complete(record)) = monitor.push(0x585, &response).unwrap() else {
+            panic!("Download did not complete");
+        };
+        assert_eq!(
+            record,
+            Record {
+                node: 5,
+                index: 0x6040,
+                subindex: 0,
+                transfer: Transfer::Download,
+                data: vec![0x0F, 0x00]
+            }
+        );
+        assert_eq!(record.as_u32(), Some(0x000F));
+
+        // Segmented upload of 0x1008:00, the device name
+        let name = b"Motor controller";
+        let frames = [
+            (
+                0x605,
+                Sdo::InitiateUpload {
+                    index: 0x1008,
+                    subindex: 0,
+                },
+            ),
+            (
+                0x585,
+                Sdo::InitiateUploadResponse {
+                    index: 0x1008,
+                    subindex: 0,
+                    data: Initiate::Segmented {
+                        size: Some(name.len() as u32),
+                    },
+                },
+            ),
+        ];
+        for (id, sdo) in frames {
+            assert_eq!(monitor.push(id, &sdo.to_frame().unwrap()).unwrap(), None);
+        }
+        let mut events = Vec::new();
+        for (i, chunk) in name.chunks(7).enumerate() {
+            let toggle = i % 2 == 1;
+            let request = Sdo::UploadSegment { toggle }.to_frame().unwrap();
+            assert_eq!(monitor.push(0x605, &request).unwrap(), None);
+            let response = Sdo::UploadSegmentResponse {
+                toggle,
+                last: (i + 1) * 7 >= name.len(),
+                data: chunk.to_vec(),
+            };
+            let frame = response.to_frame().unwrap();
+            assert_eq!(Sdo::from_response(frame).unwrap(), response);
+            events.extend(monitor.push(0x585, &frame).unwrap());
+        }
+        let [Event::Complete(record)] = events.as_slice() else {
+            panic!("Upload did not complete");
+        };
+        assert_eq!(record.transfer, Transfer::Upload);
+        assert_eq!(record.index, 0x1008);
+        assert_eq!(record.data, name);
+
+        // A repeated toggle bit means a lost segment
+        let segmented = Sdo::InitiateDownload {
+            index: 0x2000,
+            subindex: 1,
+            data: Initiate::Segmented { size: None },
+        };
+        monitor.push(0x605, &segmented.to_frame().unwrap()).unwrap();
+        let response = Sdo::InitiateDownloadResponse {
+            index: 0x2000,
+            subindex: 1,
+        };
+        monitor.push(0x585, &response.to_frame().unwrap()).unwrap();
+        let segment = Sdo::DownloadSegment {
+            toggle: false,
+            last: false,
+            data: vec![1; 7],
+        };
+        monitor.push(0x605, &segment.to_frame().unwrap()).unwrap();
+        assert!(monitor.push(0x605, &segment.to_frame().unwrap()).is_err());
+
+        // Object does not exist
+        let abort = [0x80, 0x00, 0x30, 0x00, 0x00, 0x00, 0x02, 0x06];
+        assert_eq!(
+            monitor.push(0x585, &abort).unwrap(),
+            Some(Event::Aborted {
+                node: 5,
+                index: 0x3000,
+                subindex: 0,
+                code: 0x0602_0000
+            })
+        );
+        // Not SDO
+        assert_eq!(monitor.push(0x185, &frame).unwrap(), None);
+    }
+
+    /// PDO structs generated from tests/data/motor_controller.dcf
+    mod motor_controller {
+        use proc_macro_derive_crate::{CanDecode, CanEncode};
+
+        include!("../tests/data/motor_controller_pdos.rs");
+    }
+
+    #[test]
+    fn canopen_eds() {
+        use can_extract::canopen::eds::{DataType, Eds};
+        use can_extract::canopen::Pdo;
+        use can_extract::{CanDecode, CanEncode, CanLayout};
+        use motor_controller::{MotorRpdo1, MotorRpdo2, MotorTpdo1, MotorTpdo2};
+
+        let eds =
+            Eds::parse(include_str!("../tests/data/motor_controller.dcf")).expect("Did not parse");
+        assert_eq!(eds.node_id(), Some(5));
+        assert_eq!(eds.get(0x6041, 0, "parametername"), Some("Statusword"));
+        // The DCF's ParameterValue wins over the DefaultValue
+        assert_eq!(eds.value(0x1801, 1, 5), Some(0x381));
+        assert_eq!(eds.value(0x1800, 1, 5), Some(0x185));
+
+        // TPDO3 and TPDO5 map nothing and TPDO4 is switched off
+        let pdos = eds.pdos(5).unwrap();
+        assert_eq!(pdos.len(), 4);
+        // The frame bit is masked off the 29 bit COB-ID
+        assert_eq!(pdos[1].cob_id, 0x18FF_0005);
+        let tpdo2 = &pdos[3];
+        assert_eq!(
+            (tpdo2.number, tpdo2.transmit, tpdo2.cob_id),
+            (2, true, 0x381)
+        );
+        // The dummy entry pads the limit switch out to bit 16
+        assert_eq!(tpdo2.objects[1].data_type, None);
+        assert_eq!(tpdo2.objects[2].start_bit, 16);
+        assert_eq!(tpdo2.objects[2].data_type, Some(DataType::Boolean));
+
+        // Regenerate tests/data/motor_controller_pdos.rs if this changes on purpose
+        assert_eq!(
+            eds.generate(5, "Motor").unwrap(),
+            include_str!("../tests/data/motor_controller_pdos.rs")
+        );
+
+        assert_eq!(MotorRpdo1::cob_id(), 0x205);
+        assert_eq!(MotorTpdo1::cob_id(), 0x185);
+        assert_eq!(MotorTpdo2::ID, Some(0x381));
+        assert_eq!(MotorRpdo2::ID, Some(0x18FF_0005));
+
+        let frame = [0x37, 0x06, 0x18, 0xFC, 0xFF, 0xFF, 0x00, 0x00];
+        let tpdo1 = MotorTpdo1::from_canopen(0x185, frame)
+            .unwrap()
+            .expect("Did not match the COB-ID");
+        assert_eq!(tpdo1.statusword, 0x0637);
+        assert_eq!(tpdo1.velocity_actual_value, -1000);
+
+        let tpdo2 = MotorTpdo2 {
+            modes_of_operation_display: -3,
+            limit_switch: true,
+            motor_temperature: 61.5,
+        };
+        let frame = tpdo2.to_socketcan().unwrap();
+        assert_eq!(frame[0], 0xFD);
+        assert_eq!(MotorTpdo2::from_socketcan(frame).unwrap(), tpdo2);
+
+        assert!(Eds::parse("[1A00sub0]\nno equals sign").is_err());
+        // Strings can't be mapped into a PDO
+        let eds = Eds::parse(
+            "[1A00sub0]\nDefaultValue=1\n[1A00sub1]\nDefaultValue=0x10080008\n\
+             [1008]\nParameterName=Device name\nDataType=0x0009",
+        )
+        .unwrap();
+        assert!(eds.pdos(5).is_err());
+    }
+
+    #[test]
+    fn obd2() {
+        use can_extract::obd2::{
+            self, CoolantTemperature, EngineSpeed, Pid, Request, Response, ShortTermFuelTrimBank1,
+            SupportedPids, VehicleSpeed,
+        };
+        use can_extract::CanDecodeSlice;
+
+        let request = Request::current::<EngineSpeed>();
+        assert_eq!(request.to_payload(), [0x01, 0x0C]);
+        assert_eq!(
+            request.to_frame(),
+            [0x02, 0x01, 0x0C, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC]
+        );
+        assert_eq!(obd2::FUNCTIONAL_ID, 0x7DF);
+        assert_eq!(obd2::request_id(0x7E9), Some(0x7E1));
+        assert_eq!(obd2::request_id(0x7DF), None);
+
+        // (256 * 0x1A + 0xF8) / 4
+        let response = Response::from_frame([0x04, 0x41, 0x0C, 0x1A, 0xF8, 0xAA, 0xAA, 0xAA])
+            .expect("Did not decode correctly");
+        let speed = response
+            .decode::<EngineSpeed>()
+            .unwrap()
+            .expect("Did not match the PID");
+        assert!(speed.speed.approx_eq(1726.0, (0.0, 2)));
+        assert_eq!(response.decode::<VehicleSpeed>().unwrap(), None);
+        assert_eq!(response.to_payload(), [0x41, 0x0C, 0x1A, 0xF8]);
+
+        let response = Response::from_slice(&[0x41, 0x05, 0x7B]).unwrap();
+        let coolant = response.decode::<CoolantTemperature>().unwrap().unwrap();
+        assert!(coolant.temperature.approx_eq(83.0, (0.0, 2)));
+
+        // 0x80 is stoichiometric, under it the ECU is taking fuel out
+        let response = Response::from_slice(&[0x41, 0x06, 0x70]).unwrap();
+        let trim = response
+            .decode::<ShortTermFuelTrimBank1>()
+            .unwrap()
+            .unwrap();
+        assert!(trim.trim.approx_eq(-12.5, (0.0, 2)));
+
+        let response = Response::from_slice(&[0x41, 0x42, 0x30, 0x39]).unwrap();
+        let voltage = response.decode::<ControlModuleVoltage>().unwrap().unwrap();
+        assert!(voltage.voltage.approx_eq(12.345, (0.0, 2)));
+
+        // Freeze frames carry the frame number ahead of the data
+        let request = Request::freeze_frame::<VehicleSpeed>(0);
+        assert_eq!(request.to_payload(), [0x02, 0x0D, 0x00]);
+        let response = Response::from_slice(&[0x42, 0x0D, 0x00, 0x58]).unwrap();
+        assert_eq!(response.frame, Some(0));
+        assert_eq!(
+            response.decode::<VehicleSpeed>().unwrap(),
+            Some(VehicleSpeed { speed: 88 })
+        );
+
+        let response = Response::from_slice(&[0x41, 0x20, 0x80, 0x01, 0x00, 0x01]).unwrap();
+        let supported = response.decode::<SupportedPids>().unwrap().unwrap();
+        assert_eq!(
+            supported.pids(response.pid).collect::<Vec<_>>(),
+            [0x21, 0x30, 0x40]
+        );
+        assert!(supported.supports(0x20, 0x40));
+        assert!(!supported.supports(0x00, 0x40));
+
+        // Negative responses and other services aren't service 01 or 02
+        assert!(Response::from_slice(&[0x7F, 0x01, 0x12]).is_err());
+        assert!(Response::from_slice(&[0x43, 0x01]).is_err());
+        // The first frame of a longer response
+        assert!(Response::from_frame([0x10, 0x14, 0x49, 0x02, 0x01, 0x31, 0x47, 0x31]).is_err());
+    }
+
+    #[test]
+    fn uds() {
+        use can_extract::uds::{
+            DataIdentifier, Dtc, DtcInformation, DtcReport, Nrc, Request, Response, Session,
+        };
+        use can_extract::{CanDecodeSlice, CanEncodeSlice, Error};
+        use std::time::Duration;
+
+        let request = Request::ReadDataByIdentifier {
+            dids: vec![BatteryStatus::DID],
+        };
+        assert_eq!(request.to_payload().unwrap(), [0x22, 0x01, 0x00]);
+        assert_eq!(Request::from_slice(&[0x22, 0x01, 0x00]).unwrap(), request);
+
+        let response = Response::from_slice(&[0x62, 0x01, 0x00, 0x30, 0x39, 0x50]).unwrap();
+        let battery = response
+            .decode_did::<BatteryStatus>()
+            .expect("Did not decode correctly")
+            .expect("Did not match the DID");
+        assert!(battery.voltage.approx_eq(12.345, (0.0, 2)));
+        assert_eq!(battery.state_of_charge, 80);
+        assert_eq!(
+            response.to_payload().unwrap(),
+            [0x62, 0x01, 0x00, 0x30, 0x39, 0x50]
+        );
+
+        // The DID isn't supported
+        let response = Response::from_slice(&[0x7F, 0x22, 0x31]).unwrap();
+        assert_eq!(
+            response,
+            Response::Negative {
+                sid: 0x22,
+                nrc: Nrc::RequestOutOfRange
+            }
+        );
+        assert_eq!(response.decode_did::<BatteryStatus>().unwrap(), None);
+
+        // Records can be longer than a CAN frame, their length is the response's
+        let vin = *b"1G1JC5444R7252367";
+        let response = Response::ReadDataByIdentifier {
+            did: VehicleIdentification::DID,
+            data: vin.to_vec(),
+        };
+        let record = response
+            .decode_did::<VehicleIdentification>()
+            .expect("Did not decode correctly")
+            .expect("Did not match the DID");
+        assert_eq!(record.vin, vin);
+        assert_eq!(record.to_vec().unwrap(), vin);
+        assert_eq!(VehicleIdentification::MIN_LEN, 17);
+        assert!(VehicleIdentification::from_slice(&vin[..16]).is_err());
+
+        let request = Request::from_slice(&[0x10, 0x83]).unwrap();
+        assert_eq!(
+            request,
+            Request::DiagnosticSessionControl {
+                session: Session::Extended,
+                suppress_response: true
+            }
+        );
+        assert_eq!(request.to_payload().unwrap(), [0x10, 0x83]);
+        let response = Response::from_slice(&[0x50, 0x03, 0x00, 0x32, 0x01, 0xF4]).unwrap();
+        assert_eq!(
+            response,
+            Response::DiagnosticSessionControl {
+                session: Session::Extended,
+                p2: Duration::from_millis(50),
+                p2_extended: Duration::from_millis(5000)
+            }
+        );
+        assert_eq!(
+            response.to_payload().unwrap(),
+            [0x50, 0x03, 0x00, 0x32, 0x01, 0xF4]
+        );
+
+        let request = Request::ReadDtcInformation(DtcReport::ByStatusMask(0x09));
+        assert_eq!(request.to_payload().unwrap(), [0x19, 0x02, 0x09]);
+        let payload = [
+            0x59, 0x02, 0xFF, 0xC1, 0x23, 0x00, 0x09, 0x01, 0x56, 0x78, 0x08,
+        ];
+        let response = Response::from_slice(&payload).unwrap();
+        assert_eq!(
+            response,
+            Response::ReadDtcInformation(DtcInformation::Dtcs {
+                sub_function: 0x02,
+                availability_mask: 0xFF,
+                dtcs: vec![
+                    Dtc {
+                        code: 0xC1_2300,
+                        status: 0x09
+                    },
+                    Dtc {
+                        code: 0x01_5678,
+                        status: 0x08
+                    },
+                ]
+            })
+        );
+        assert_eq!(response.to_payload().unwrap(), payload);
+        // A partial DTC record left over
+        assert!(matches!(
+            Response::from_slice(&payload[..10]),
+            Err(Error::InvalidSlicingLength)
+        ));
+        assert!(matches!(
+            Request::from_slice(&[0x22, 0x01, 0x00, 0x02]),
+            Err(Error::InvalidSlicingLength)
+        ));
+
+        // Values the layout can't hold aren't truncated
+        let response = Response::DiagnosticSessionControl {
+            session: Session::Extended,
+            p2: Duration::from_secs(70),
+            p2_extended: Duration::from_millis(5000),
+        };
+        assert!(matches!(response.to_payload(), Err(Error::ValueOutOfRange)));
+        let response = Response::DiagnosticSessionControl {
+            session: Session::Extended,
+            p2: Duration::from_millis(50),
+            p2_extended: Duration::from_secs(700),
+        };
+        assert!(matches!(response.to_payload(), Err(Error::ValueOutOfRange)));
+        let request = Request::ClearDiagnosticInformation { group: 0x0100_0000 };
+        assert!(matches!(request.to_payload(), Err(Error::ValueOutOfRange)));
+
+        assert!(Response::from_slice(&[]).is_err());
+        assert!(Response::from_slice(&[0x50, 0x03]).is_err());
+    }
+
+    #[test]
+    fn bit_matrix() {
+        use can_extract::helper::{extract_bits, insert_bits};
+        use can_extract::ByteOrder::{BigEndian, LittleEndian};
+
+        // DBC definition, packed frame, raw value
+        let matrix = [
+            (
+                "7|16@0+",
+                7,
+                16,
+                BigEndian,
+                [0x12, 0x34, 0, 0, 0, 0, 0, 0],
+                0x1234,
+            ),
+            (
+                "0|16@1+",
+                0,
+                16,
+                LittleEndian,
+                [0x34, 0x12, 0, 0, 0, 0, 0, 0],
+                0x1234,
+            ),
+            (
+                "12|12@0+",
+                12,
+                12,
+                BigEndian,
+                [0, 0x15, 0x78, 0, 0, 0, 0, 0],
+                0xABC,
+            ),
+            (
+                "12|12@1+",
+                12,
+                12,
+                LittleEndian,
+                [0, 0xC0, 0xAB, 0, 0, 0, 0, 0],
+                0xABC,
+            ),
+            ("3|4@0+", 3, 4, BigEndian, [0x0A, 0, 0, 0, 0, 0, 0, 0], 0xA),
+            (
+                "39|32@0+",
+                39,
+                32,
+                BigEndian,
+                [0, 0, 0, 0, 0xDE, 0xAD, 0xBE, 0xEF],
+                0xDEADBEEF,
+            ),
+            (
+                "60|4@1+",
+                60,
+                4,
+                LittleEndian,
+                [0, 0, 0, 0, 0, 0, 0, 0x50],
+                0x5,
+            ),
+            (
+                "7|64@0+",
+                7,
+                64,
+                BigEndian,
+                [1, 2, 3, 4, 5, 6, 7, 8],
+                0x0102030405060708,
+            ),
+        ];
+        for (dbc, start_bit, length, byte_order, frame, raw) in matrix {
+            let extracted = extract_bits(&frame, start_bit, length, byte_order).unwrap();
+            assert_eq!(extracted, raw, "{}", dbc);
+            let mut packed = [0; 8];
+            insert_bits(&mut packed, start_bit, length, byte_order, raw).unwrap();
+            assert_eq!(packed, frame, "{}", dbc);
+        }
+
+        // Motorola signals running off the end of the frame
+        assert!(extract_bits(&[0; 8], 56, 9, BigEndian).is_err());
+    }
+
+    #[test]
+    fn codecs() {
+        use can_extract::codec::FieldCodec;
+        use can_extract::{CanLayout, Scaling};
+
+        let data: [u8; 8] = [0x30, 0x39, 0x23, 0x03, 0xF6, 0x0F, 0, 0];
+        let val: CodecStruct =
+            can_extract::CanDecode::from_socketcan(data).expect("Did not decode correctly");
+        assert!(val.distance.approx_eq(12.345, (0.0, 2)));
+        assert_eq!(val.hour, 23);
+        assert_eq!(val.gear, Gear::Drive);
+        assert_eq!(val.temperature, -45.0);
+        assert_eq!(can_extract::CanEncode::to_socketcan(&val).unwrap(), data);
+
+        let scaling = CodecStruct::SIGNALS[0].scaling.unwrap();
+        assert_eq!(scaling.factor, 1.0 / 1000.0);
+        assert!(CodecStruct::SIGNALS[3].signed);
+
+        // Too many digits for the signal is an error, even past the 16 that fit in a u64
+        assert_eq!(
+            <Bcd as FieldCodec<u64>>::encode(&9_999, 16).unwrap(),
+            0x9999
+        );
+        assert!(matches!(
+            <Bcd as FieldCodec<u64>>::encode(&10_000, 16),
+            Err(can_extract::Error::ValueOutOfRange)
+        ));
+        assert!(matches!(
+            <Bcd as FieldCodec<u64>>::encode(&u64::MAX, 64),
+            Err(can_extract::Error::ValueOutOfRange)
+        ));
+
+        can_extract::assert_round_trip!(CodecStruct);
+    }
+
+    #[test]
+    fn lazy_view() {
+        let data: [u8; 8] = [5, 0, 0, 5, 0, 0, 0, 1];
+        let view = TestStructView::new(&data);
+        assert_eq!(view.b().unwrap(), 5);
+        assert!(view.c().unwrap().approx_eq(0.001, (0.0, 2)));
+        assert!(TestStructView::new(&data[..4]).c().is_err());
+    }
+
+    #[test]
+    fn layout() {
+        use can_extract::{ByteOrder, CanLayout, Scaling};
+
+        assert_eq!(TestStruct::ID, Some(0x123));
+        assert_eq!(TestStruct::NAME, "TestStruct");
+        assert_eq!(TestStruct::DLC, Some(8));
+
+        let [a, b, c] = TestStruct::SIGNALS else {
+            panic!("Expected 3 signals");
+        };
+        assert_eq!((a.name, a.start_bit, a.length), ("a", 0, 16));
+        assert_eq!(a.scaling, Some(Scaling::IDENTITY));
+        assert_eq!((b.start_bit, b.byte_order), (23, ByteOrder::BigEndian));
+        assert_eq!((c.start_bit, c.length, c.type_name), (48, 16, "f32"));
+        assert_eq!(c.scaling, None);
+        assert_eq!(OdometerStruct::SIGNALS[0].unit, Some("km"));
+    }
+
+    #[test]
+    fn e2e_check() {
+        use can_extract::e2e::{Checker, Protector, Status};
+
+        let mut protector = Protector::for_message::<ProtectedStruct>();
+        let mut checker = Checker::for_message::<ProtectedStruct>();
+        let mut frames = [[0, 0, 7, 0, 0, 0, 0, 0]; 4];
+        for frame in frames.iter_mut() {
+            protector.protect(frame).unwrap();
+        }
+
+        let (status, val) = checker
+            .check_decode::<ProtectedStruct>(Some(frames[0]))
+            .unwrap();
+        assert_eq!(status, Status::Ok);
+        assert_eq!(val.unwrap().speed, 7);
+        assert_eq!(checker.check(Some(&frames[0])), Status::Repeated);
+        assert_eq!(checker.check(Some(&frames[1])), Status::Ok);
+        assert_eq!(checker.check(Some(&frames[3])), Status::WrongSequence);
+        assert_eq!(checker.check(None), Status::NoNewData);
+
+        frames[2][4] ^= 0x01;
+        assert_eq!(checker.check(Some(&frames[2])), Status::Error);
+    }
+
+    #[test]
+    fn e2e_known_answers() {
+        use can_extract::e2e::{Config, DataIdMode, Profile, Protector};
+
+        // CRC bytes for an empty payload with data ID 0x123 - the profile 1 CRC is SAE J1850's
+        // polynomial with a zero start and final XOR, profile 2's is CRC-8/AUTOSAR (0x2F)
+        let profile1 = |mode| Profile::Profile1 {
+            data_id: 0x123,
+            mode,
+        };
+        let profile2 = Profile::Profile2 {
+            data_id_list: core::array::from_fn(|index| index as u8 + 1),
+        };
+        for (profile, expected) in [
+            // The worked example in the AUTOSAR E2E spec
+            (profile1(DataIdMode::Both), [0xCC, 0x91, 0x76]),
+            // Odd counters take the high byte of the data ID instead
+            (profile1(DataIdMode::Alt), [0xCE, 0x02, 0x74]),
+            (profile1(DataIdMode::Low), [0xCE, 0x93, 0x74]),
+            (profile2, [0x0E, 0x1B, 0x98]),
+        ] {
+            let mut protector = Protector::new(Config {
+                profile,
+                max_delta_counter: 1,
+            });
+            for (counter, crc) in expected.into_iter().enumerate() {
+                let mut frame = [0; 8];
+                protector.protect(&mut frame).unwrap();
+                assert_eq!(frame[..2], [crc, counter as u8], "{:?}", profile);
+            }
+        }
+
+        // Profile 2 counts all the way to 15 before wrapping
+        let mut protector = Protector::new(Config {
+            profile: profile2,
+            max_delta_counter: 1,
+        });
+        let mut frame = [0; 8];
+        for _ in 0..16 {
+            protector.protect(&mut frame).unwrap();
+        }
+        assert_eq!(frame[..2], [0xCD, 15]);
+        protector.protect(&mut frame).unwrap();
+        assert_eq!(frame[..2], [0x0E, 0]);
+    }
+
+    #[test]
+    fn e2e_profile2() {
+        use can_extract::e2e::{Checker, Protector, Status};
+
+        let mut protector = Protector::for_message::<Protected2Struct>();
+        let mut frames = [[0, 0, 7, 0, 0, 0, 0, 0]; 40];
+        for frame in frames.iter_mut() {
+            protector.protect(frame).unwrap();
+        }
+        assert_eq!(frames[15][1], 15);
+        assert_eq!(frames[16][1], 0);
+
+        // Every counter value in turn, wrapping from 15 back to 0
+        let mut checker = Checker::for_message::<Protected2Struct>();
+        for frame in &frames[..20] {
+            assert_eq!(checker.check(Some(frame)), Status::Ok);
+        }
+        // One lost frame is tolerated, across the wrap too, but two aren't
+        assert_eq!(checker.check(Some(&frames[21])), Status::Ok);
+        assert_eq!(checker.check(Some(&frames[24])), Status::WrongSequence);
+        assert_eq!(checker.check(Some(&frames[30])), Status::WrongSequence);
+        assert_eq!(checker.check(Some(&frames[32])), Status::Ok);
+        assert_eq!(checker.check(Some(&frames[32])), Status::Repeated);
+
+        // The data ID depends on the counter, so a frame with its counter changed fails the CRC
+        let mut frame = frames[33];
+        frame[1] = 3;
+        assert_eq!(checker.check(Some(&frame)), Status::Error);
+        let (status, value) = checker
+            .check_decode::<Protected2Struct>(Some(frames[33]))
+            .unwrap();
+        assert_eq!(status, Status::Ok);
+        assert_eq!(value.unwrap().speed, 7);
+    }
 }
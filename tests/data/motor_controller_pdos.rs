@@ -0,0 +1,53 @@
+/// RPDO1 of node 5, mapped by 0x1600
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq, Eq)]
+#[can_extract(pdo = 1, node = 0x05, rpdo)]
+pub struct MotorRpdo1 {
+    /// 0x6040:00 Controlword
+    #[can_extract(offset = 0)]
+    pub controlword: u16,
+    /// 0x60FF:00 Target velocity
+    #[can_extract(offset = 2)]
+    pub target_velocity: i32,
+}
+
+/// RPDO2 of node 5, mapped by 0x1601
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq, Eq)]
+#[can_extract(id = 0x18FF0005)]
+pub struct MotorRpdo2 {
+    /// 0x6040:00 Controlword
+    #[can_extract(offset = 0)]
+    pub controlword: u16,
+    /// 0x6040:00 Controlword
+    #[can_extract(offset = 2)]
+    pub object_6040_00: u16,
+    /// 0x6040:00 Controlword
+    #[can_extract(offset = 4)]
+    pub object_6040_00_2: u16,
+}
+
+/// TPDO1 of node 5, mapped by 0x1A00
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq, Eq)]
+#[can_extract(pdo = 1, node = 0x05)]
+pub struct MotorTpdo1 {
+    /// 0x6041:00 Statusword
+    #[can_extract(offset = 0)]
+    pub statusword: u16,
+    /// 0x606C:00 Velocity actual value
+    #[can_extract(offset = 2)]
+    pub velocity_actual_value: i32,
+}
+
+/// TPDO2 of node 5, mapped by 0x1A01
+#[derive(CanDecode, CanEncode, Debug, Clone, Copy, PartialEq)]
+#[can_extract(id = 0x381)]
+pub struct MotorTpdo2 {
+    /// 0x6061:00 Modes of operation display
+    #[can_extract(offset = 0)]
+    pub modes_of_operation_display: i8,
+    /// 0x2001:01 Limit switch
+    #[can_extract(start_bit = 16, length = 1)]
+    pub limit_switch: bool,
+    /// 0x2002:00 Motor temperature
+    #[can_extract(start_bit = 17, ieee_float)]
+    pub motor_temperature: f32,
+}
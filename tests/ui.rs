@@ -0,0 +1,7 @@
+/// Checks which messages the derive accepts, and the errors it gives for those it doesn't
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/pass/*.rs");
+    cases.compile_fail("tests/ui/fail/*.rs");
+}
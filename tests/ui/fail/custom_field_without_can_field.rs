@@ -0,0 +1,13 @@
+use proc_macro_derive_crate::CanDecode;
+
+#[derive(Debug)]
+struct Percent(u8);
+
+// Members of your own types need a CanField impl, or a codec or decoder
+#[derive(CanDecode, Debug)]
+struct Throttle {
+    #[can_extract(offset = 0)]
+    position: Percent,
+}
+
+fn main() {}
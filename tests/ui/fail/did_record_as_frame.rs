@@ -0,0 +1,15 @@
+use can_extract::{CanDecode, CanEncode};
+use proc_macro_derive_crate::{CanDecode, CanEncode};
+
+// UDS records can be longer than a CAN frame, so are only read from and written to slices
+#[derive(CanDecode, CanEncode, Debug)]
+#[can_extract(did = 0xF190, dlc = 17)]
+struct VehicleIdentification {
+    #[can_extract(offset = 0)]
+    vin: [u8; 17],
+}
+
+fn main() {
+    let vin = VehicleIdentification::from_socketcan([0; 8]).unwrap();
+    let _ = vin.to_socketcan();
+}
@@ -0,0 +1,11 @@
+use proc_macro_derive_crate::CanDecode;
+
+// The odometer reaches byte 4, past the declared two byte payload
+#[derive(CanDecode, Debug)]
+#[can_extract(dlc = 2)]
+struct Odometer {
+    #[can_extract(offset = 0)]
+    distance: u32,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use can_extract::{CanDecode, Signal};
+use proc_macro_derive_crate::CanDecode;
+
+#[derive(CanDecode, Debug)]
+struct Status<S> {
+    #[can_extract(start_bit = 0, length = 4)]
+    status: Signal<S>,
+}
+
+fn main() {
+    // Signals are checked against their raw value, which floats need a codec for
+    let _ = Status::<f32>::from_socketcan([0; 8]);
+}
@@ -0,0 +1,13 @@
+use can_extract::CanDecode;
+use proc_macro_derive_crate::CanDecode;
+
+#[derive(CanDecode, Debug)]
+struct Reading<T> {
+    #[can_extract(offset = 0)]
+    value: T,
+}
+
+fn main() {
+    // A String can't be read from the frame's bits
+    let _ = Reading::<String>::from_socketcan([0; 8]);
+}
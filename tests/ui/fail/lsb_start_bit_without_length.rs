@@ -0,0 +1,11 @@
+use proc_macro_derive_crate::CanDecode;
+
+// Finding the MSB from the LSB takes the signal's length
+#[derive(CanDecode, Debug)]
+#[can_extract(byte_order = "big", bit_numbering = "lsb")]
+struct Status {
+    #[can_extract(start_bit = 12)]
+    mode: u8,
+}
+
+fn main() {}
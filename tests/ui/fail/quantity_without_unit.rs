@@ -0,0 +1,11 @@
+use can_extract::uom;
+use proc_macro_derive_crate::CanDecode;
+
+// The frame's unit has to be spelled out, it can't be guessed from the quantity
+#[derive(CanDecode, Debug)]
+struct WheelSpeed {
+    #[can_extract(offset = 0, extract = 2, factor = 0.01)]
+    front: uom::si::f32::Velocity,
+}
+
+fn main() {}
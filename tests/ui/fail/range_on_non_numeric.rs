@@ -0,0 +1,10 @@
+use proc_macro_derive_crate::CanDecode;
+
+// A bool has no physical value to compare
+#[derive(CanDecode, Debug)]
+struct Status {
+    #[can_extract(start_bit = 0, length = 1, min = 0, max = 1)]
+    enabled: bool,
+}
+
+fn main() {}
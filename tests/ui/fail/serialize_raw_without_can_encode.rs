@@ -0,0 +1,11 @@
+use proc_macro_derive_crate::CanDecode;
+
+// The raw value is read back out of the encoded payload, which needs CanEncode derived
+#[derive(CanDecode, Debug)]
+#[can_extract(serialize)]
+struct Odometer {
+    #[can_extract(offset = 0, extract = 4, serialize_raw)]
+    distance: u32,
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use can_extract::{CanDecode, CanEncode, CanLayout};
+use proc_macro_derive_crate::{CanDecode, CanEncode};
+
+// What Eds::generate writes for tests/data/motor_controller.dcf, checked by the canopen_eds test
+include!("../../data/motor_controller_pdos.rs");
+
+fn main() {
+    let rpdo2 = MotorRpdo2 {
+        controlword: 0x0F,
+        object_6040_00: 0x1F,
+        object_6040_00_2: 0x3F,
+    };
+    let frame = rpdo2.to_socketcan().unwrap();
+    assert_eq!(MotorRpdo2::from_socketcan(frame).unwrap(), rpdo2);
+    assert_eq!(MotorRpdo2::ID, Some(0x18FF_0005));
+    let _ = (MotorRpdo1::ID, MotorTpdo1::ID, MotorTpdo2::ID);
+}
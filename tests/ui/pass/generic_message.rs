@@ -0,0 +1,42 @@
+use can_extract::codec::Scaled;
+use can_extract::{ByteOrder, CanDecode, CanEncode, CanField, Error, Signal};
+use proc_macro_derive_crate::{CanDecode, CanEncode};
+
+/// Each member is bound on what it's read through, so none of them need to be messages
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+struct Reading<T, S, C> {
+    #[can_extract(offset = 0)]
+    value: T,
+    #[can_extract(start_bit = 32, length = 4)]
+    status: S,
+    #[can_extract(offset = 5, not_available = 0xFF)]
+    quality: Signal<S>,
+    #[can_extract(offset = 6, extract = 2, codec = Scaled<1, 10>)]
+    scaled: C,
+}
+
+/// Implementing CanField carries a type of your own in a frame
+#[derive(Debug, PartialEq)]
+struct Millivolts(u32);
+
+impl CanField for Millivolts {
+    const BITS: u16 = 32;
+
+    fn decode(frame: &[u8], start_bit: u16, length: u16, byte_order: ByteOrder) -> Result<Self, Error> {
+        u32::decode(frame, start_bit, length, byte_order).map(Millivolts)
+    }
+
+    fn encode(&self, frame: &mut [u8], start_bit: u16, length: u16, byte_order: ByteOrder) -> Result<(), Error> {
+        self.0.encode(frame, start_bit, length, byte_order)
+    }
+}
+
+fn main() {
+    let frame = [1, 0, 0, 0, 3, 90, 100, 0];
+    let reading = Reading::<u32, u8, f32>::from_socketcan(frame).unwrap();
+    assert_eq!(reading.scaled, 10.0);
+    assert_eq!(reading.to_socketcan().unwrap(), frame);
+
+    let reading = Reading::<Millivolts, u16, f64>::from_socketcan(frame).unwrap();
+    assert_eq!(reading.value, Millivolts(1));
+}
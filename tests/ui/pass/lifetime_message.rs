@@ -0,0 +1,26 @@
+use can_extract::{CanDecode, CanDecodeSlice, Error};
+use proc_macro_derive_crate::CanDecode;
+
+fn mode(bytes: &[u8]) -> Result<&'static str, Error> {
+    ["Off", "On"]
+        .get(bytes[0] as usize)
+        .copied()
+        .ok_or(Error::ValueOutOfRange)
+}
+
+/// Lifetimes need no bounds, and mix with type parameters
+#[derive(CanDecode, Debug)]
+#[can_extract(dlc = 2)]
+struct Labelled<'a, T> {
+    #[can_extract(offset = 0)]
+    value: T,
+    #[can_extract(offset = 1, extract = 1, use_decoder = "mode")]
+    mode: &'a str,
+}
+
+fn main() {
+    let labelled = Labelled::<u8>::from_slice(&[7, 1]).unwrap();
+    assert_eq!((labelled.value, labelled.mode), (7, "On"));
+    let labelled = Labelled::<i8>::from_socketcan([7, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+    assert_eq!(labelled.mode, "Off");
+}
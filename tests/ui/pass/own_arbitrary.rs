@@ -0,0 +1,25 @@
+use can_extract::proptest::prelude::*;
+use can_extract::{CanDecode, CanEncode};
+use proc_macro_derive_crate::{CanDecode, CanEncode};
+
+/// Untagged, so Arbitrary is left to us - here keeping the gear in its real range
+#[derive(CanDecode, CanEncode, Debug, PartialEq)]
+struct Transmission {
+    #[can_extract(offset = 0)]
+    gear: u8,
+}
+
+impl Arbitrary for Transmission {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: ()) -> Self::Strategy {
+        (0u8..=6).prop_map(|gear| Transmission { gear }).boxed()
+    }
+}
+
+fn main() {
+    let _strategy = any::<Transmission>();
+    let frame = Transmission { gear: 3 }.to_socketcan().unwrap();
+    assert_eq!(Transmission::from_socketcan(frame).unwrap().gear, 3);
+}
@@ -0,0 +1,21 @@
+use can_extract::serde::{Serialize, Serializer};
+use can_extract::CanDecode;
+use proc_macro_derive_crate::CanDecode;
+
+/// Untagged, so Serialize is left to us - here just the speed, without the signal metadata
+#[derive(CanDecode, Debug)]
+struct Speed {
+    #[can_extract(offset = 0)]
+    kph: u8,
+}
+
+impl Serialize for Speed {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.kph)
+    }
+}
+
+fn main() {
+    let speed = Speed::from_socketcan([88, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+    assert_eq!(speed.kph, 88);
+}
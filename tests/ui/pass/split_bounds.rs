@@ -0,0 +1,35 @@
+use can_extract::{CanDecode, CanEncode, Error};
+use proc_macro_derive_crate::{CanDecode, CanEncode};
+
+fn decode_gear<T: From<u8>>(bytes: &[u8]) -> Result<T, Error> {
+    Ok(T::from(bytes[0]))
+}
+
+fn encode_gear<T: Copy + Into<u8>>(value: &T, bytes: &mut [u8]) -> Result<(), Error> {
+    bytes[0] = (*value).into();
+    Ok(())
+}
+
+/// Decoders and encoders can each ask something different of a member
+#[derive(CanDecode, CanEncode, Debug)]
+#[can_extract(decode_bounds = "T: From<u8>", encode_bounds = "T: Copy + Into<u8>")]
+struct Gearbox<T> {
+    #[can_extract(offset = 0, extract = 1, use_decoder = "decode_gear", use_encoder = "encode_gear")]
+    gear: T,
+}
+
+/// Only ever decoded, so can't be bound on what encoding it takes
+struct Label(u8);
+
+impl From<u8> for Label {
+    fn from(value: u8) -> Self {
+        Label(value)
+    }
+}
+
+fn main() {
+    let gearbox = Gearbox::<u8>::from_socketcan([4, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+    assert_eq!(gearbox.to_socketcan().unwrap()[0], 4);
+    let gearbox = Gearbox::<Label>::from_socketcan([3, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+    assert_eq!(gearbox.gear.0, 3);
+}